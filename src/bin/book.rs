@@ -0,0 +1,156 @@
+//! Builds the opening book by searching every position near the start of the game deeply.
+//!
+//! ```shell
+//! cargo run --release --bin book -- [OPTIONS]
+//! ```
+//!
+//! Options:
+//! - `--mode avalanche|capture`, only build the book for these rules. Defaults to both.
+//! - `--plies N`, how many moves into the game the book reaches. Defaults to 8.
+//! - `--depth N`, how many moves ahead each position is searched. Defaults to 12.
+//! - `--margin N`, how many seeds worse than the best a move can be and still make the book.
+//!   Defaults to 2.
+//! - `--output PATH`, where to write the book. Defaults to `assets/book.txt`, which is built in.
+
+use bevy_mancala::{
+    game::{
+        ai::{
+            book::{BookMove, OpeningBook},
+            search::{Search, TranspositionTable},
+        },
+        rules::Position,
+        Player,
+    },
+    states::GameMode,
+};
+use std::{collections::VecDeque, env, fs, process::ExitCode};
+
+const USAGE: &str = "usage: book [--mode avalanche|capture] [--plies N] [--depth N] [--margin N] \
+                     [--output PATH]";
+
+/// Big enough to keep a deep search from forgetting what it's already seen.
+const TABLE_MEGABYTES: usize = 256;
+
+struct Options {
+    modes: Vec<GameMode>,
+    plies: u32,
+    depth: u32,
+    margin: i32,
+    output: String,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            modes: GameMode::iter().collect(),
+            plies: 8,
+            depth: 12,
+            margin: 2,
+            output: "assets/book.txt".to_string(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+
+            match arg.as_str() {
+                "--mode" => {
+                    let value = value()?;
+                    options.modes = vec![value
+                        .to_uppercase()
+                        .parse()
+                        .map_err(|_| format!("unknown mode {value}"))?];
+                }
+                "--plies" => {
+                    options.plies = value()?.parse().map_err(|_| "--plies takes a number")?;
+                }
+                "--depth" => {
+                    options.depth = value()?.parse().map_err(|_| "--depth takes a number")?;
+                }
+                "--margin" => {
+                    options.margin = value()?.parse().map_err(|_| "--margin takes a number")?;
+                }
+                "--output" => options.output = value()?,
+                _ => return Err(format!("unknown option {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Adds every position within `plies` moves of the start to `book`, following only the moves it
+/// keeps.
+fn build(book: &mut OpeningBook, game_mode: GameMode, options: &Options) {
+    let mut table = TranspositionTable::new(TABLE_MEGABYTES);
+    let mut search = Search::new(game_mode, None, &mut table);
+    let mut queue: VecDeque<(Position, u32)> = Player::iter()
+        .map(|player| (Position::new(player), 0))
+        .collect();
+
+    while let Some((position, ply)) = queue.pop_front() {
+        if ply >= options.plies || position.is_over() || book.contains(&position, game_mode) {
+            continue;
+        }
+
+        let scores: Vec<(usize, i32)> = position
+            .legal_moves()
+            .map(|index| {
+                let score = search.score_move(&position, index, options.depth, -i32::MAX, i32::MAX);
+
+                (index, score)
+            })
+            .collect();
+
+        let Some(best) = scores.iter().map(|(_, score)| *score).max() else {
+            continue;
+        };
+
+        // the best moves weigh the most, and the rest less the more they give away
+        let moves: Vec<BookMove> = scores
+            .into_iter()
+            .filter(|(_, score)| best - score <= options.margin)
+            .map(|(index, score)| BookMove {
+                index,
+                weight: (options.margin - (best - score) + 1) as u32,
+            })
+            .collect();
+
+        for book_move in &moves {
+            let mut child = position.clone();
+            child.play(book_move.index, game_mode);
+
+            queue.push_back((child, ply + 1));
+        }
+
+        book.insert(position, game_mode, moves);
+
+        eprint!("\r{game_mode}: {} positions", book.len());
+    }
+
+    eprintln!();
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut book = OpeningBook::default();
+
+    for game_mode in options.modes.iter().copied() {
+        build(&mut book, game_mode, &options);
+    }
+
+    if let Err(error) = fs::write(&options.output, book.to_string()) {
+        eprintln!("failed to write {}: {error}", options.output);
+        return ExitCode::FAILURE;
+    }
+
+    println!("wrote {} positions to {}", book.len(), options.output);
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,371 @@
+//! Plays AI agents against each other without a window, then reports how they fared.
+//!
+//! ```shell
+//! cargo run --release --bin tournament -- [OPTIONS] AGENT AGENT...
+//! ```
+//!
+//! An agent is a difficulty of the built-in CPU (`easy`, `medium`, `hard` or `perfect`), the name
+//! of a registered agent like `mcts`, optionally followed by a difficulty as in `mcts:hard`, or
+//! otherwise the command line of an engine speaking the protocol in `game::ai::engine`, quoted if
+//! it takes arguments.
+//!
+//! Options:
+//! - `--mode avalanche|capture`, the rules to play by. Defaults to avalanche.
+//! - `--games N`, how many games each pairing plays, with seats swapped every game. Defaults to 10.
+//! - `--gauntlet`, pairs the first agent against each of the others instead of everyone against
+//!   everyone.
+//! - `--move-time MS`, how long engines get per move. Defaults to 5000.
+//! - `--seed N`, seeds everything random so the tournament can be played again exactly, barring
+//!   engines. Defaults to a random seed, which is printed.
+
+use bevy::tasks::{AsyncComputeTaskPool, TaskPool};
+use bevy_mancala::{
+    game::{
+        ai::{
+            agent::{AgentRegistry, AgentSettings, MancalaAgent},
+            endgame::EndgameTable,
+            engine::EngineAgent,
+            Difficulty, AI_NAME,
+        },
+        rules::Position,
+        Player,
+    },
+    profile::{self, EngineConfig},
+    states::GameMode,
+};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+use std::{env, process::ExitCode, sync::Arc, task::Poll, thread, time::Duration};
+
+const USAGE: &str = "usage: tournament [--mode avalanche|capture] [--games N] [--gauntlet] \
+                     [--move-time MS] [--seed N] AGENT AGENT...";
+
+struct Options {
+    mode: GameMode,
+    games: u32,
+    gauntlet: bool,
+    move_time_ms: u64,
+    seed: u64,
+    agents: Vec<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            mode: GameMode::Avalanche,
+            games: 10,
+            gauntlet: false,
+            move_time_ms: 5000,
+            seed: rand::random(),
+            agents: vec![],
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+
+            match arg.as_str() {
+                "--mode" => {
+                    let value = value()?;
+                    options.mode = value
+                        .to_uppercase()
+                        .parse()
+                        .map_err(|_| format!("unknown mode {value}"))?;
+                }
+                "--games" => {
+                    options.games = value()?.parse().map_err(|_| "--games takes a number")?;
+                }
+                "--gauntlet" => options.gauntlet = true,
+                "--move-time" => {
+                    options.move_time_ms = value()?
+                        .parse()
+                        .map_err(|_| "--move-time takes milliseconds")?;
+                }
+                "--seed" => {
+                    options.seed = value()?.parse().map_err(|_| "--seed takes a number")?;
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+                _ => options.agents.push(arg),
+            }
+        }
+
+        if options.agents.len() < 2 {
+            return Err("at least two agents are needed".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+enum Spec {
+    Registered(String, Difficulty),
+    Engine(String),
+}
+
+impl Spec {
+    fn parse(spec: &str, registry: &AgentRegistry) -> Self {
+        let upper = spec.to_uppercase();
+
+        if let Ok(difficulty) = upper.parse() {
+            return Self::Registered(AI_NAME.to_string(), difficulty);
+        }
+
+        let (name, difficulty) = match upper.split_once(':') {
+            Some((name, difficulty)) => (name, difficulty.parse()),
+            None => (upper.as_str(), Ok(Difficulty::default())),
+        };
+
+        match difficulty {
+            Ok(difficulty) if registry.names().any(|other| other == name) => {
+                Self::Registered(name.to_string(), difficulty)
+            }
+            _ => Self::Engine(spec.to_string()),
+        }
+    }
+
+    /// Each agent keeps its own tables, so what one has searched doesn't help the other.
+    fn create(
+        &self,
+        registry: &AgentRegistry,
+        endgame: Option<&Arc<EndgameTable>>,
+        move_time_ms: u64,
+        seed: u64,
+    ) -> Result<Box<dyn MancalaAgent>, String> {
+        let command_line = match self {
+            Self::Registered(name, difficulty) => {
+                let settings = AgentSettings {
+                    difficulty: *difficulty,
+                    personality: None,
+                    endgame: endgame.cloned(),
+                    seed,
+                };
+
+                return registry
+                    .create(name, &settings)
+                    .ok_or(format!("no agent called {name}"));
+            }
+            Self::Engine(command_line) => command_line,
+        };
+
+        let mut words = command_line.split_whitespace().map(str::to_string);
+        let command = words.next().ok_or("empty agent")?;
+        let config = EngineConfig {
+            command,
+            args: words.collect(),
+            move_time_ms,
+        };
+
+        EngineAgent::launch(&config)
+            .map(|agent| Box::new(agent) as _)
+            .map_err(|error| format!("failed to launch {command_line}: {error}"))
+    }
+}
+
+/// Picks a move, which like in the game is random if an agent gives up or doesn't answer legally.
+fn choose(
+    agent: &mut dyn MancalaAgent,
+    position: &Position,
+    mode: GameMode,
+    rng: &mut StdRng,
+) -> usize {
+    agent.think(position, mode);
+
+    let choice = loop {
+        match agent.poll() {
+            Poll::Ready(choice) => break choice,
+            Poll::Pending => thread::sleep(Duration::from_millis(1)),
+        }
+    };
+
+    choice
+        .filter(|index| position.is_legal(*index))
+        .or_else(|| position.legal_moves().choose(rng))
+        .expect("the game is over once a side has no moves")
+}
+
+/// Plays a game to the end with the same rules as the game, returning the winner.
+fn play(
+    seats: [&mut Box<dyn MancalaAgent>; 2],
+    mode: GameMode,
+    rng: &mut StdRng,
+) -> Option<Player> {
+    let [one, two] = seats;
+    let mut position = Position::new(Player::One);
+
+    while !position.is_over() {
+        let agent = match position.current {
+            Player::One => &mut *one,
+            Player::Two => &mut *two,
+        };
+
+        let index = choose(agent.as_mut(), &position, mode, rng);
+        position.play(index, mode);
+    }
+
+    position.sweep(mode);
+    position.leader()
+}
+
+/// Mutably borrows two different agents at once.
+fn pair<T>(agents: &mut [T], a: usize, b: usize) -> [&mut T; 2] {
+    if a < b {
+        let (left, right) = agents.split_at_mut(b);
+        [&mut left[a], &mut right[0]]
+    } else {
+        let (left, right) = agents.split_at_mut(a);
+        [&mut right[0], &mut left[b]]
+    }
+}
+
+/// The score `a` is expected to take off `b`, between 0 and 1.
+fn expected_score(a: f64, b: f64) -> f64 {
+    1. / (1. + 10f64.powf((b - a) / 400.))
+}
+
+/// Ratings that best explain the results, centred on zero. Each rating is nudged until its
+/// expected score matches the actual one, and clamped so a perfect score doesn't run off.
+fn elo(results: &[Vec<[u32; 3]>]) -> Vec<f64> {
+    let mut ratings = vec![0.; results.len()];
+
+    for _ in 0..2000 {
+        for a in 0..results.len() {
+            let (mut actual, mut expected, mut games) = (0., 0., 0.);
+
+            for (b, [wins, draws, losses]) in results[a].iter().enumerate() {
+                let played = f64::from(wins + draws + losses);
+
+                actual += f64::from(*wins) + f64::from(*draws) / 2.;
+                expected += played * expected_score(ratings[a], ratings[b]);
+                games += played;
+            }
+
+            if games > 0. {
+                ratings[a] = (ratings[a] + 32. * (actual - expected) / games).clamp(-1000., 1000.);
+            }
+        }
+    }
+
+    let mean = ratings.iter().sum::<f64>() / ratings.len() as f64;
+
+    ratings.iter().map(|rating| rating - mean).collect()
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    eprintln!("seed {}", options.seed);
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+
+    // the minimax agents' searches run here, but MCTS searches in the background
+    AsyncComputeTaskPool::get_or_init(TaskPool::default);
+
+    let registry = AgentRegistry::default();
+    let specs: Vec<Spec> = options
+        .agents
+        .iter()
+        .map(|spec| Spec::parse(spec, &registry))
+        .collect();
+
+    // only the perfect CPU needs the endgame table, which takes a while to solve the first time
+    let config_dir = profile::config_dir();
+    let endgame = specs
+        .iter()
+        .any(|spec| matches!(spec, Spec::Registered(_, Difficulty::Perfect)))
+        .then(|| Arc::new(EndgameTable::load_or_build(&config_dir, options.mode)));
+
+    let agents: Result<Vec<Box<dyn MancalaAgent>>, String> = specs
+        .iter()
+        .map(|spec| spec.create(&registry, endgame.as_ref(), options.move_time_ms, rng.gen()))
+        .collect();
+
+    let mut agents = match agents {
+        Ok(agents) => agents,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let count = agents.len();
+    let pairings: Vec<(usize, usize)> = if options.gauntlet {
+        (1..count).map(|b| (0, b)).collect()
+    } else {
+        (0..count)
+            .flat_map(|a| (a + 1..count).map(move |b| (a, b)))
+            .collect()
+    };
+
+    // wins, draws and losses of each agent against each other agent
+    let mut results = vec![vec![[0; 3]; count]; count];
+
+    for (a, b) in pairings.iter().copied() {
+        for game in 0..options.games {
+            // swap seats every game so both get to move first
+            let (first, second) = if game % 2 == 0 { (a, b) } else { (b, a) };
+            let winner = play(pair(&mut agents, first, second), options.mode, &mut rng);
+
+            let outcome = match winner {
+                Some(Player::One) => [first, second],
+                Some(Player::Two) => [second, first],
+                None => {
+                    results[a][b][1] += 1;
+                    results[b][a][1] += 1;
+                    continue;
+                }
+            };
+
+            results[outcome[0]][outcome[1]][0] += 1;
+            results[outcome[1]][outcome[0]][2] += 1;
+        }
+
+        let [wins, draws, losses] = results[a][b];
+        let name = |index: usize| &options.agents[index];
+
+        println!("{} vs {}: {wins} W {draws} D {losses} L", name(a), name(b));
+    }
+
+    let ratings = elo(&results);
+    let width = options
+        .agents
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max(5);
+
+    println!(
+        "\n{} games per pairing, {} mode\n",
+        options.games, options.mode
+    );
+    println!(
+        "{:<width$} {:>6} {:>5} {:>5} {:>5} {:>7} {:>6}",
+        "AGENT", "GAMES", "W", "D", "L", "SCORE", "ELO"
+    );
+
+    let mut standings: Vec<usize> = (0..count).collect();
+    standings.sort_by(|a, b| ratings[*b].total_cmp(&ratings[*a]));
+
+    for index in standings {
+        let [wins, draws, losses] = results[index]
+            .iter()
+            .fold([0; 3], |total, row| [0, 1, 2].map(|i| total[i] + row[i]));
+        let games = wins + draws + losses;
+        let score = if games == 0 {
+            0.
+        } else {
+            (f64::from(wins) + f64::from(draws) / 2.) / f64::from(games) * 100.
+        };
+
+        println!(
+            "{:<width$} {games:>6} {wins:>5} {draws:>5} {losses:>5} {score:>6.1}% {:>+6.0}",
+            options.agents[index], ratings[index]
+        );
+    }
+
+    ExitCode::SUCCESS
+}
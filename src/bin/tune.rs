@@ -0,0 +1,275 @@
+//! Tunes the weights the search scores positions with by having it play itself, then writes them
+//! out for the game to load.
+//!
+//! ```shell
+//! cargo run --release --bin tune -- [OPTIONS]
+//! ```
+//!
+//! Starting from weights that only count the stores, every generation nudges the weights at random
+//! and keeps the nudge if it wins clearly more games than it loses against the weights so far. The same
+//! options always tune the same weights.
+//!
+//! The store stays at a seed, `SCALE`, since that's what the search scores finished games and
+//! endgame table hits with, and everything else is tuned against it. Captures are only tuned in
+//! capture mode, the only one they're counted in.
+//!
+//! The search is shallow by default, as deep as the Medium CPU, since the deeper it reads the fewer
+//! moves the weights decide: at depth 4 no avalanche nudge changed enough games to be kept.
+//!
+//! Options:
+//! - `--mode avalanche|capture`, only tune the weights for these rules. Defaults to both.
+//! - `--seed N`, where the random nudges and openings start from. Defaults to 0.
+//! - `--generations N`, how many nudges are tried. Defaults to 200.
+//! - `--games N`, how many games each match plays, in pairs with the same opening and seats
+//!   swapped. Defaults to 400, enough that `KEEP_MARGIN` is well clear of luck.
+//! - `--depth N`, how many moves ahead both sides search. Defaults to 3.
+//! - `--step N`, the most a weight is nudged by, in hundredths of a seed. Defaults to 40.
+//! - `--output PATH`, where to write the weights. Defaults to `assets/weights.txt`, which is built
+//!   in. Modes that aren't tuned keep their built-in weights.
+
+use bevy_mancala::{
+    game::{
+        ai::{
+            search::{Search, TranspositionTable},
+            weights::{Feature, TunedWeights, Weights},
+        },
+        rules::Position,
+        Player,
+    },
+    states::GameMode,
+};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+use std::{env, fs, process::ExitCode};
+
+const USAGE: &str = "usage: tune [--mode avalanche|capture] [--seed N] [--generations N] \
+                     [--games N] [--depth N] [--step N] [--output PATH]";
+
+/// Random moves played before each pair of games, so the deterministic search doesn't play the
+/// same game every time.
+const OPENING_PLIES: u32 = 2;
+/// How many more games than it loses, out of every hundred, a nudge has to win to be kept, so
+/// weights don't wander off on lucky matches.
+const KEEP_MARGIN: u32 = 10;
+const TABLE_MEGABYTES: usize = 4;
+
+struct Options {
+    modes: Vec<GameMode>,
+    seed: u64,
+    generations: u32,
+    games: u32,
+    depth: u32,
+    step: i32,
+    output: String,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            modes: GameMode::iter().collect(),
+            seed: 0,
+            generations: 200,
+            games: 400,
+            depth: 3,
+            step: 40,
+            output: "assets/weights.txt".to_string(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+
+            match arg.as_str() {
+                "--mode" => {
+                    let value = value()?;
+                    options.modes = vec![value
+                        .to_uppercase()
+                        .parse()
+                        .map_err(|_| format!("unknown mode {value}"))?];
+                }
+                "--seed" => {
+                    options.seed = value()?.parse().map_err(|_| "--seed takes a number")?;
+                }
+                "--generations" => {
+                    options.generations = value()?
+                        .parse()
+                        .map_err(|_| "--generations takes a number")?;
+                }
+                "--games" => {
+                    options.games = value()?.parse().map_err(|_| "--games takes a number")?;
+                }
+                "--depth" => {
+                    options.depth = value()?.parse().map_err(|_| "--depth takes a number")?;
+                }
+                "--step" => {
+                    options.step = value()?.parse().map_err(|_| "--step takes a number")?;
+                }
+                "--output" => options.output = value()?,
+                _ => return Err(format!("unknown option {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// One side of a match, keeping its own table so the other's searches don't help it.
+struct Side {
+    weights: Weights,
+    table: TranspositionTable,
+}
+
+impl Side {
+    fn new(weights: Weights) -> Self {
+        Self {
+            weights,
+            table: TranspositionTable::new(TABLE_MEGABYTES),
+        }
+    }
+}
+
+/// Plays `opening` out to the end, returning the winner.
+fn play(
+    seats: [&mut Side; 2],
+    opening: &Position,
+    game_mode: GameMode,
+    depth: u32,
+) -> Option<Player> {
+    let [one, two] = seats;
+    let mut position = opening.clone();
+
+    while !position.is_over() {
+        let side = match position.current {
+            Player::One => &mut *one,
+            Player::Two => &mut *two,
+        };
+
+        let (index, _) = Search::with_weights(game_mode, side.weights, None, &mut side.table)
+            .best_move(&position, depth)
+            .expect("the game is over once a side has no moves");
+
+        position.play(index, game_mode);
+    }
+
+    position.sweep(game_mode);
+    position.leader()
+}
+
+/// Wins, draws and losses of `challenger` against `champion`.
+fn play_match(
+    challenger: Weights,
+    champion: Weights,
+    game_mode: GameMode,
+    options: &Options,
+    rng: &mut StdRng,
+) -> [u32; 3] {
+    let mut challenger = Side::new(challenger);
+    let mut champion = Side::new(champion);
+    let mut results = [0; 3];
+
+    for _ in 0..options.games.div_ceil(2) {
+        let mut opening = Position::new(Player::One);
+
+        for _ in 0..OPENING_PLIES {
+            if let Some(index) = opening.legal_moves().choose(rng) {
+                opening.play(index, game_mode);
+            }
+        }
+
+        // both sides get each seat of the same opening
+        for challenger_seat in Player::iter() {
+            let seats = match challenger_seat {
+                Player::One => [&mut challenger, &mut champion],
+                Player::Two => [&mut champion, &mut challenger],
+            };
+
+            let result = match play(seats, &opening, game_mode, options.depth) {
+                Some(winner) if winner == challenger_seat => 0,
+                None => 1,
+                Some(_) => 2,
+            };
+
+            results[result] += 1;
+        }
+    }
+
+    results
+}
+
+/// `weights` with every weight that's tuned for `game_mode` nudged by up to `step`, never below
+/// zero.
+fn nudge(weights: Weights, game_mode: GameMode, step: i32, rng: &mut StdRng) -> Weights {
+    let mut weights = weights;
+
+    let tuned = Feature::iter().filter(|feature| match feature {
+        Feature::Store => false,
+        Feature::Captures => game_mode == GameMode::Capture,
+        _ => true,
+    });
+
+    for feature in tuned {
+        let weight = weights.get(feature) + rng.gen_range(-step..=step);
+
+        weights.set(feature, weight.max(0));
+    }
+
+    weights
+}
+
+fn tune(game_mode: GameMode, options: &Options) -> Weights {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut champion = Weights::default();
+
+    for generation in 1..=options.generations {
+        let challenger = nudge(champion, game_mode, options.step, &mut rng);
+
+        if challenger == champion {
+            continue;
+        }
+
+        let [wins, draws, losses] = play_match(challenger, champion, game_mode, options, &mut rng);
+
+        if wins > losses + options.games * KEEP_MARGIN / 100 {
+            champion = challenger;
+
+            eprintln!(
+                "{game_mode} generation {generation}: {wins} W {draws} D {losses} L, now {:?}",
+                champion.0
+            );
+        }
+    }
+
+    champion
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut weights = TunedWeights::builtin().clone();
+
+    for game_mode in options.modes.iter().copied() {
+        let tuned = tune(game_mode, &options);
+
+        // a fresh match, so the weights are judged on openings they weren't picked on
+        let mut rng = StdRng::seed_from_u64(options.seed.wrapping_add(1));
+        let [wins, draws, losses] =
+            play_match(tuned, Weights::default(), game_mode, &options, &mut rng);
+
+        println!("{game_mode}: against the stores alone, {wins} W {draws} D {losses} L");
+
+        weights.insert(game_mode, tuned);
+    }
+
+    if let Err(error) = fs::write(&options.output, weights.to_string()) {
+        eprintln!("failed to write {}: {error}", options.output);
+        return ExitCode::FAILURE;
+    }
+
+    println!("wrote weights to {}", options.output);
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,186 @@
+//! Anything that picks moves for a CPU seat, and the names they're picked by.
+//!
+//! An agent is told about the position once its turn starts, then asked every frame whether it has
+//! a move yet, so it can answer straight away or work in the background. More agents can be added
+//! with `AgentRegistry::register` once `AiPlugin` is in, and each gets a profile of its own.
+
+use super::{
+    endgame::EndgameTable, mcts::MctsAgent, personality::Personality, search::TranspositionTable,
+    Difficulty, AI_NAME,
+};
+use crate::{game::rules::Position, profile::ProfileKind, states::GameMode};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+use std::{sync::Arc, task::Poll};
+
+pub trait MancalaAgent: Send + Sync {
+    /// Starts picking a move for the player to move in `position`.
+    fn think(&mut self, position: &Position, game_mode: GameMode);
+
+    /// The move picked, once there is one. `None` gives up, and a random move is played instead.
+    fn poll(&mut self) -> Poll<Option<usize>>;
+}
+
+/// What an agent is made with, from the seat it plays.
+#[derive(Clone, Default)]
+pub struct AgentSettings {
+    pub difficulty: Difficulty,
+    pub personality: Option<Personality>,
+    /// The endgame table for the game's rules, if it was ready when the game started.
+    pub endgame: Option<Arc<EndgameTable>>,
+    /// Where anything random about the agent's play starts from.
+    pub seed: u64,
+}
+
+type AgentFactory = Box<dyn Fn(&AgentSettings) -> Box<dyn MancalaAgent> + Send + Sync>;
+
+/// Every agent that can be picked, by name.
+#[derive(Resource)]
+pub struct AgentRegistry {
+    agents: Vec<(String, AgentFactory)>,
+}
+
+impl AgentRegistry {
+    /// Adds an agent, replacing any other with the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn(&AgentSettings) -> Box<dyn MancalaAgent> + Send + Sync + 'static,
+    ) {
+        let name = name.into();
+
+        self.agents.retain(|(other, _)| *other != name);
+        self.agents.push((name, Box::new(factory)));
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.agents.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn create(&self, name: &str, settings: &AgentSettings) -> Option<Box<dyn MancalaAgent>> {
+        self.agents
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, factory)| factory(settings))
+    }
+}
+
+impl Default for AgentRegistry {
+    fn default() -> Self {
+        let mut registry = Self { agents: vec![] };
+
+        registry.register(AI_NAME, |settings| match settings.difficulty {
+            Difficulty::Easy => Box::new(RandomAgent::new(settings.seed)),
+            _ => Box::new(MinimaxAgent::new(settings)),
+        });
+        registry.register("MCTS", |settings| {
+            Box::new(MctsAgent::new(settings.difficulty, settings.seed))
+        });
+
+        registry
+    }
+}
+
+/// What each CPU seat was picked to be, made into agents when the game starts.
+#[derive(Resource, Default)]
+pub struct AgentChoice(pub [Option<ProfileKind>; 2]);
+
+/// The agents playing the CPU seats, emptied once the game is left.
+#[derive(Resource, Default)]
+pub struct Agents(pub [Option<Box<dyn MancalaAgent>>; 2]);
+
+/// Plays any legal move.
+pub struct RandomAgent {
+    rng: StdRng,
+    choice: Option<usize>,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            choice: None,
+        }
+    }
+}
+
+impl MancalaAgent for RandomAgent {
+    fn think(&mut self, position: &Position, _game_mode: GameMode) {
+        self.choice = position.legal_moves().choose(&mut self.rng);
+    }
+
+    fn poll(&mut self) -> Poll<Option<usize>> {
+        Poll::Ready(self.choice.take())
+    }
+}
+
+/// The built-in CPU, searching in the background as deep as its difficulty allows.
+pub struct MinimaxAgent {
+    difficulty: Difficulty,
+    personality: Option<Personality>,
+    endgame: Option<Arc<EndgameTable>>,
+    /// Kept from one move to the next, and lent to the search while it runs.
+    table: Option<TranspositionTable>,
+    rng: StdRng,
+    task: Option<Task<(Option<usize>, TranspositionTable)>>,
+}
+
+impl MinimaxAgent {
+    pub fn new(settings: &AgentSettings) -> Self {
+        Self {
+            difficulty: settings.difficulty,
+            personality: settings.personality,
+            endgame: settings.endgame.clone(),
+            table: Some(TranspositionTable::default()),
+            rng: StdRng::seed_from_u64(settings.seed),
+            task: None,
+        }
+    }
+}
+
+impl MancalaAgent for MinimaxAgent {
+    fn think(&mut self, position: &Position, game_mode: GameMode) {
+        let position = position.clone();
+        let difficulty = self.difficulty;
+        let personality = self.personality;
+        let endgame = self
+            .endgame
+            .clone()
+            .filter(|endgame| endgame.game_mode == game_mode);
+        let mut table = self.table.take().unwrap_or_default();
+        // each search gets its own generator, so it doesn't matter when the task gets to run
+        let mut rng = StdRng::seed_from_u64(self.rng.gen());
+
+        // the deeper difficulties take too long to search between frames
+        self.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            let choice = difficulty.choose(
+                &position,
+                game_mode,
+                personality,
+                endgame.as_deref(),
+                &mut table,
+                &mut rng,
+            );
+
+            (choice, table)
+        }));
+    }
+
+    fn poll(&mut self) -> Poll<Option<usize>> {
+        let Some(task) = &mut self.task else {
+            return Poll::Ready(None);
+        };
+
+        match block_on(future::poll_once(task)) {
+            Some((choice, table)) => {
+                self.task = None;
+                self.table = Some(table);
+                Poll::Ready(choice)
+            }
+            None => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Good first moves worked out ahead of time, so the CPU can open instantly and not always the
+//! same way.
+//!
+//! A book is a text file starting with the line `MANCALA BOOK 1`, followed by positions:
+//! - `POSITION <MODE> <player> <c0> ... <c13>` with the mode (`AVALANCHE` or `CAPTURE`), the player
+//!   to move (`1` or `2`) and the count of every pit in board order, stores included.
+//! - One `MOVE <index> <weight>` line after it for every move worth playing there, picked with a
+//!   chance proportional to its weight.
+//!
+//! `cargo run --release --bin book` writes a new one to `assets/book.txt`, which is built in.
+
+use crate::{
+    game::{rules::Position, Board, Player},
+    states::GameMode,
+};
+use bevy::log::warn;
+use rand::{seq::SliceRandom, Rng};
+use std::{collections::HashMap, fmt, str::FromStr, sync::OnceLock};
+
+const HEADER: &str = "MANCALA BOOK 1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    pub index: usize,
+    pub weight: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookError {
+    Malformed(usize),
+    /// The line names a mode this build doesn't know.
+    UnknownMode(usize),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed(line) => write!(f, "malformed line {}", line + 1),
+            Self::UnknownMode(line) => write!(f, "unknown mode on line {}", line + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    entries: HashMap<(GameMode, Position), Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    /// The book shipped with the game.
+    pub fn builtin() -> &'static Self {
+        static BOOK: OnceLock<OpeningBook> = OnceLock::new();
+
+        BOOK.get_or_init(|| {
+            include_str!("../../../assets/book.txt")
+                .parse()
+                .inspect_err(|error| warn!("failed to read opening book: {error}"))
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, position: &Position, game_mode: GameMode) -> bool {
+        self.entries.contains_key(&(game_mode, position.clone()))
+    }
+
+    pub fn insert(&mut self, position: Position, game_mode: GameMode, moves: Vec<BookMove>) {
+        self.entries.insert((game_mode, position), moves);
+    }
+
+    /// Picks one of the book's moves for `position` by weight, if it has any legal ones.
+    pub fn choose(
+        &self,
+        position: &Position,
+        game_mode: GameMode,
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        let moves: Vec<BookMove> = self
+            .entries
+            .get(&(game_mode, position.clone()))?
+            .iter()
+            .copied()
+            .filter(|book_move| position.is_legal(book_move.index))
+            .collect();
+
+        moves
+            .choose_weighted(rng, |book_move| book_move.weight)
+            .ok()
+            .map(|book_move| book_move.index)
+    }
+}
+
+impl fmt::Display for OpeningBook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+
+        // sorted, so regenerating the same book gives the same file
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|((game_mode, position), _)| {
+            (*game_mode as u8, position.current as u8, position.counts)
+        });
+
+        for ((game_mode, position), moves) in entries {
+            write!(f, "POSITION {game_mode} {}", position.current as usize + 1)?;
+
+            for count in position.counts {
+                write!(f, " {count}")?;
+            }
+
+            writeln!(f)?;
+
+            for book_move in moves {
+                writeln!(f, "MOVE {} {}", book_move.index, book_move.weight)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for OpeningBook {
+    type Err = BookError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+
+        if lines.next() != Some(HEADER) {
+            return Err(BookError::Malformed(0));
+        }
+
+        let mut book = Self::default();
+        let mut current: Option<(GameMode, Position)> = None;
+        let mut moves = vec![];
+
+        for (number, line) in lines.enumerate().map(|(number, line)| (number + 1, line)) {
+            if line.is_empty() {
+                continue;
+            }
+
+            let (kind, rest) = line.split_once(' ').ok_or(BookError::Malformed(number))?;
+
+            match kind {
+                "POSITION" => {
+                    if let Some(key) = current.take() {
+                        book.entries.insert(key, std::mem::take(&mut moves));
+                    }
+
+                    current = Some(parse_position(rest, number)?);
+                }
+                "MOVE" if current.is_some() => {
+                    let (index, weight) =
+                        rest.split_once(' ').ok_or(BookError::Malformed(number))?;
+
+                    moves.push(BookMove {
+                        index: index.parse().map_err(|_| BookError::Malformed(number))?,
+                        weight: weight.parse().map_err(|_| BookError::Malformed(number))?,
+                    });
+                }
+                _ => return Err(BookError::Malformed(number)),
+            }
+        }
+
+        if let Some(key) = current {
+            book.entries.insert(key, moves);
+        }
+
+        Ok(book)
+    }
+}
+
+fn parse_position(text: &str, number: usize) -> Result<(GameMode, Position), BookError> {
+    let mut parts = text.split(' ');
+
+    let game_mode = parts
+        .next()
+        .ok_or(BookError::Malformed(number))?
+        .parse()
+        .map_err(|_| BookError::UnknownMode(number))?;
+
+    let current = match parts.next() {
+        Some("1") => Player::One,
+        Some("2") => Player::Two,
+        _ => return Err(BookError::Malformed(number)),
+    };
+
+    let counts: Vec<u32> = parts
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| BookError::Malformed(number))?;
+
+    let counts: [u32; Board::LENGTH] = counts
+        .try_into()
+        .map_err(|_| BookError::Malformed(number))?;
+
+    Ok((game_mode, Position { counts, current }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const BUILTIN: &str = include_str!("../../../assets/book.txt");
+
+    fn book() -> OpeningBook {
+        let mut book = OpeningBook::default();
+        let start = Position::new(Player::One);
+        let mut played = start.clone();
+        played.play(2, GameMode::Capture);
+
+        book.insert(
+            start.clone(),
+            GameMode::Capture,
+            vec![
+                BookMove {
+                    index: 2,
+                    weight: 1,
+                },
+                BookMove {
+                    index: 5,
+                    weight: 3,
+                },
+            ],
+        );
+        book.insert(
+            start,
+            GameMode::Avalanche,
+            vec![BookMove {
+                index: 0,
+                weight: 1,
+            }],
+        );
+        book.insert(
+            played,
+            GameMode::Capture,
+            vec![BookMove {
+                index: 9,
+                weight: 2,
+            }],
+        );
+
+        book
+    }
+
+    #[test]
+    fn books_survive_the_round_trip() {
+        let book = book();
+        let text = book.to_string();
+        let parsed: OpeningBook = text.parse().unwrap();
+
+        assert_eq!(parsed.entries, book.entries);
+        assert_eq!(parsed.to_string(), text);
+    }
+
+    #[test]
+    fn the_builtin_book_is_valid() {
+        let book: OpeningBook = BUILTIN.parse().unwrap();
+
+        assert!(!book.is_empty());
+        assert_eq!(book.to_string(), BUILTIN);
+
+        for ((_, position), moves) in &book.entries {
+            assert!(!moves.is_empty(), "{position:?}");
+
+            for book_move in moves {
+                assert!(
+                    position.is_legal(book_move.index),
+                    "{position:?} {book_move:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn moves_are_picked_by_weight() {
+        let book = book();
+        let position = Position::new(Player::One);
+        let mut rng = StdRng::seed_from_u64(1);
+        let picks = 4000;
+
+        let heavier = (0..picks)
+            .filter(|_| book.choose(&position, GameMode::Capture, &mut rng) == Some(5))
+            .count();
+
+        // weighted 3 to 1, so about three in four
+        assert!((2850..=3150).contains(&heavier), "{heavier} of {picks}");
+    }
+
+    #[test]
+    fn only_legal_moves_in_known_positions_are_picked() {
+        let mut book = book();
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut position = Position::new(Player::One);
+        position.counts[5] = 0;
+
+        book.insert(
+            position.clone(),
+            GameMode::Capture,
+            vec![
+                BookMove {
+                    index: 5,
+                    weight: 100,
+                },
+                BookMove {
+                    index: 1,
+                    weight: 1,
+                },
+            ],
+        );
+
+        for _ in 0..100 {
+            assert_eq!(book.choose(&position, GameMode::Capture, &mut rng), Some(1));
+        }
+
+        position.counts[1] = 0;
+
+        assert_eq!(book.choose(&position, GameMode::Capture, &mut rng), None);
+        assert_eq!(
+            book.choose(&Position::new(Player::Two), GameMode::Capture, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn broken_books_are_refused() {
+        let text = book().to_string();
+
+        assert_eq!(
+            text.replacen(HEADER, "MANCALA BOOK 2", 1)
+                .parse::<OpeningBook>()
+                .err(),
+            Some(BookError::Malformed(0))
+        );
+        assert_eq!(
+            text.replacen("AVALANCHE", "OWARE", 1)
+                .parse::<OpeningBook>()
+                .err(),
+            Some(BookError::UnknownMode(1))
+        );
+
+        for (from, to) in [
+            ("POSITION CAPTURE 1", "POSITION CAPTURE 3"),
+            (" 0\nMOVE", " 0 4\nMOVE"),
+            ("MOVE 2 1", "MOVE 2"),
+            ("MOVE 2 1", "MOVE TWO 1"),
+            ("MOVE 2 1", "PLAY 2 1"),
+        ] {
+            let broken = text.replacen(from, to, 1);
+
+            assert!(broken.parse::<OpeningBook>().is_err(), "{broken}");
+        }
+
+        assert_eq!(
+            format!("{HEADER}\nMOVE 2 1").parse::<OpeningBook>().err(),
+            Some(BookError::Malformed(1))
+        );
+    }
+}
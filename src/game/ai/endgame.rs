@@ -0,0 +1,484 @@
+//! Perfect play for every position with only a few seeds left in the pits, worked out ahead of
+//! time and kept on disk.
+//!
+//! What's still to be won only depends on the seeds in the pits and whose turn it is, never on the
+//! stores, so each entry is the difference the player to move makes to the final score from here
+//! on, playing perfectly against perfect play.
+
+use crate::{
+    game::{rules::Position, Board, Player},
+    profile::ConfigDir,
+    states::GameMode,
+};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Positions with up to this many seeds in the pits are solved.
+pub const ENDGAME_SEEDS: u32 = 10;
+
+const MAGIC: &[u8] = b"MANCALA ENDGAME";
+/// Bumped whenever the file changes shape.
+const VERSION: u8 = 1;
+const UNSOLVED: i8 = i8::MIN;
+
+/// Every board index that isn't a store, in board order.
+const PITS: [usize; 12] = [0, 1, 2, 3, 4, 5, 7, 8, 9, 10, 11, 12];
+
+pub struct EndgamePlugin;
+
+impl Plugin for EndgamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Endgames>()
+            .init_resource::<SolveEndgames>()
+            .add_systems(Startup, load.run_if(solving))
+            .add_systems(Update, receive.run_if(building));
+    }
+}
+
+/// The table for each game mode, once it's been loaded or solved in the background.
+#[derive(Resource, Default)]
+pub struct Endgames {
+    tables: Vec<Arc<EndgameTable>>,
+    building: Vec<Task<EndgameTable>>,
+}
+
+impl Endgames {
+    pub fn get(&self, game_mode: GameMode) -> Option<Arc<EndgameTable>> {
+        self.tables
+            .iter()
+            .find(|table| table.game_mode == game_mode)
+            .cloned()
+    }
+}
+
+/// Whether the tables are loaded or solved at startup. Inserted as `false` before the plugins are
+/// added where nothing plays perfectly, since solving keeps every core busy for a while.
+#[derive(Resource)]
+pub struct SolveEndgames(pub bool);
+
+impl Default for SolveEndgames {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+fn solving(solve: Res<SolveEndgames>) -> bool {
+    solve.0
+}
+
+fn load(mut endgames: ResMut<Endgames>, config_dir: Res<ConfigDir>) {
+    let pool = AsyncComputeTaskPool::get();
+
+    endgames.building = GameMode::iter()
+        .map(|game_mode| {
+            let dir = config_dir.0.clone();
+
+            pool.spawn(async move { EndgameTable::load_or_build(&dir, game_mode) })
+        })
+        .collect();
+}
+
+fn building(endgames: Res<Endgames>) -> bool {
+    !endgames.building.is_empty()
+}
+
+fn receive(mut endgames: ResMut<Endgames>) {
+    let endgames = endgames.as_mut();
+    let mut building = vec![];
+
+    for mut task in endgames.building.drain(..) {
+        match block_on(future::poll_once(&mut task)) {
+            Some(table) => endgames.tables.push(Arc::new(table)),
+            None => building.push(task),
+        }
+    }
+
+    endgames.building = building;
+}
+
+/// Solved values for every position with up to `max_seeds` seeds in play, under one set of rules.
+pub struct EndgameTable {
+    pub game_mode: GameMode,
+    pub max_seeds: u32,
+    /// Indexed by `index`, with the player to move alternating.
+    values: Vec<i8>,
+}
+
+impl EndgameTable {
+    /// Solves every position with up to `max_seeds` seeds in play.
+    pub fn build(game_mode: GameMode, max_seeds: u32) -> Self {
+        // values are at most the seeds in play, which have to fit an `i8`
+        let max_seeds = max_seeds.min(i8::MAX as u32);
+        let mut table = Self {
+            game_mode,
+            max_seeds,
+            values: vec![UNSOLVED; 2 * positions_up_to(max_seeds)],
+        };
+
+        let mut position = Position {
+            counts: [0; Board::LENGTH],
+            current: Player::One,
+        };
+
+        for seeds in 0..=max_seeds {
+            table.solve_all(&mut position, 0, seeds);
+        }
+
+        table
+    }
+
+    /// Solves every way of spreading `seeds` over the pits from `PITS[pit]` on.
+    fn solve_all(&mut self, position: &mut Position, pit: usize, seeds: u32) {
+        if pit == PITS.len() - 1 {
+            position.counts[PITS[pit]] = seeds;
+
+            for player in Player::iter() {
+                position.current = player;
+                self.solve(position);
+            }
+
+            return;
+        }
+
+        for count in 0..=seeds {
+            position.counts[PITS[pit]] = count;
+            self.solve_all(position, pit + 1, seeds - count);
+        }
+    }
+
+    /// The value of `position`, solving it and everything it leads to first if needed.
+    ///
+    /// Without reaching a store seeds only move along their own row, so positions with the same
+    /// seeds in play never lead back to each other and this always bottoms out.
+    fn solve(&mut self, position: &Position) -> i32 {
+        let index = index(position);
+
+        if self.values[index] != UNSOLVED {
+            return self.values[index].into();
+        }
+
+        let value = if position.is_over() {
+            terminal_value(position, self.game_mode)
+        } else {
+            let player = position.current;
+
+            position
+                .legal_moves()
+                .map(|pit| {
+                    let mut child = position.clone();
+                    child.play(pit, self.game_mode);
+
+                    let gain = child.store(player) as i32 - position.store(player) as i32;
+                    let rest = self.solve(&child);
+
+                    if child.current == player {
+                        gain + rest
+                    } else {
+                        gain - rest
+                    }
+                })
+                .max()
+                .unwrap_or(0)
+        };
+
+        self.values[index] = value as i8;
+
+        value
+    }
+
+    /// How much the player to move gains on their opponent from here with perfect play, if
+    /// `position` is small enough to be in the table.
+    pub fn get(&self, position: &Position) -> Option<i32> {
+        if seeds_in_play(position) > self.max_seeds {
+            return None;
+        }
+
+        match self.values.get(index(position)) {
+            Some(&value) if value != UNSOLVED => Some(value.into()),
+            _ => None,
+        }
+    }
+
+    /// A perfect move and the final score difference it leads to for the player to move, or
+    /// `None` if the game is over or `position` isn't in the table.
+    pub fn best_move(&self, position: &Position) -> Option<(usize, i32)> {
+        if position.is_over() {
+            return None;
+        }
+
+        self.get(position)?;
+
+        let player = position.current;
+        let mut best: Option<(usize, i32)> = None;
+
+        for pit in position.legal_moves() {
+            let mut child = position.clone();
+            child.play(pit, self.game_mode);
+
+            let rest = self.get(&child)?;
+            let rest = if child.current == player { rest } else { -rest };
+            let score = super::evaluate(&child.counts, player) + rest;
+
+            if best.is_none_or(|(_, best)| score > best) {
+                best = Some((pit, score));
+            }
+        }
+
+        best
+    }
+
+    /// Where the table for `game_mode` is kept between runs, in `dir`.
+    pub fn path(dir: &Path, game_mode: GameMode) -> PathBuf {
+        dir.join(format!(
+            "endgame-{}.bin",
+            game_mode.to_string().to_lowercase()
+        ))
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 3 + self.values.len());
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend([VERSION, self.game_mode as u8, self.max_seeds as u8]);
+        bytes.extend(self.values.iter().map(|value| *value as u8));
+
+        fs::create_dir_all(dir)?;
+        fs::write(Self::path(dir, self.game_mode), bytes)
+    }
+
+    pub fn load(dir: &Path, game_mode: GameMode) -> io::Result<Self> {
+        let bytes = fs::read(Self::path(dir, game_mode))?;
+        let invalid = || io::Error::new(ErrorKind::InvalidData, "not an endgame table");
+
+        let header = bytes.strip_prefix(MAGIC).ok_or_else(invalid)?;
+        let [version, mode, max_seeds, values @ ..] = header else {
+            return Err(invalid());
+        };
+
+        let max_seeds = u32::from(*max_seeds);
+
+        if *version != VERSION
+            || *mode != game_mode as u8
+            || values.len() != 2 * positions_up_to(max_seeds)
+        {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            game_mode,
+            max_seeds,
+            values: values.iter().map(|value| *value as i8).collect(),
+        })
+    }
+
+    /// The table saved in `dir`, or a freshly solved one saved there for next time if there's none
+    /// yet or the saved one is too small.
+    pub fn load_or_build(dir: &Path, game_mode: GameMode) -> Self {
+        if let Ok(table) = Self::load(dir, game_mode) {
+            if table.max_seeds >= ENDGAME_SEEDS {
+                return table;
+            }
+        }
+
+        let table = Self::build(game_mode, ENDGAME_SEEDS);
+
+        if let Err(error) = table.save(dir) {
+            warn!("failed to save endgame table: {error}");
+        }
+
+        table
+    }
+}
+
+/// What the player to move gains on their opponent once the game is over, which is everything
+/// left on their side in capture mode.
+fn terminal_value(position: &Position, game_mode: GameMode) -> i32 {
+    let mut swept = position.clone();
+
+    match swept.sweep(game_mode) {
+        Some((player, seeds)) if player == position.current => seeds as i32,
+        Some((_, seeds)) => -(seeds as i32),
+        None => 0,
+    }
+}
+
+fn seeds_in_play(position: &Position) -> u32 {
+    PITS.iter().map(|pit| position.counts[*pit]).sum()
+}
+
+/// `n` choose `k`.
+fn binomial(n: u32, k: u32) -> usize {
+    if k > n {
+        return 0;
+    }
+
+    let k = k.min(n - k);
+
+    (0..k).fold(1, |total, i| total * (n - i) as usize / (i + 1) as usize)
+}
+
+/// How many ways `seeds` can be spread over `pits` pits.
+fn spreads(seeds: u32, pits: u32) -> usize {
+    match pits {
+        0 => usize::from(seeds == 0),
+        _ => binomial(seeds + pits - 1, pits - 1),
+    }
+}
+
+/// How many ways up to `seeds` seeds can be spread over the pits.
+fn positions_up_to(seeds: u32) -> usize {
+    binomial(seeds + PITS.len() as u32, PITS.len() as u32)
+}
+
+/// Where `position` is kept in the table. Positions are grouped by seeds in play, and ordered
+/// within each group by their pit counts.
+fn index(position: &Position) -> usize {
+    let seeds = seeds_in_play(position);
+    let mut rank = if seeds == 0 {
+        0
+    } else {
+        positions_up_to(seeds - 1)
+    };
+    let mut remaining = seeds;
+
+    for (i, pit) in PITS.iter().enumerate() {
+        let later = (PITS.len() - i - 1) as u32;
+        let count = position.counts[*pit];
+
+        rank += (0..count)
+            .map(|skipped| spreads(remaining - skipped, later))
+            .sum::<usize>();
+        remaining -= count;
+    }
+
+    2 * rank + position.current as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::HashMap;
+
+    /// Plays every line out to the end, only remembering positions it has already seen.
+    fn brute_force(
+        position: &Position,
+        game_mode: GameMode,
+        seen: &mut HashMap<Position, i32>,
+    ) -> i32 {
+        if let Some(value) = seen.get(position) {
+            return *value;
+        }
+
+        let value = if position.is_over() {
+            terminal_value(position, game_mode)
+        } else {
+            let player = position.current;
+
+            position
+                .legal_moves()
+                .map(|pit| {
+                    let mut child = position.clone();
+                    child.play(pit, game_mode);
+
+                    let gain = child.store(player) as i32 - position.store(player) as i32;
+
+                    // the stores don't change what's left to win
+                    child.counts[Board::STORE_1] = 0;
+                    child.counts[Board::STORE_2] = 0;
+
+                    let rest = brute_force(&child, game_mode, seen);
+
+                    if child.current == player {
+                        gain + rest
+                    } else {
+                        gain - rest
+                    }
+                })
+                .max()
+                .unwrap()
+        };
+
+        seen.insert(position.clone(), value);
+
+        value
+    }
+
+    fn random_position(rng: &mut StdRng, seeds: u32) -> Position {
+        let mut position = Position {
+            counts: [0; Board::LENGTH],
+            current: if rng.gen() { Player::One } else { Player::Two },
+        };
+
+        for _ in 0..seeds {
+            position.counts[PITS[rng.gen_range(0..PITS.len())]] += 1;
+        }
+
+        position
+    }
+
+    #[test]
+    fn every_entry_is_solved() {
+        let table = EndgameTable::build(GameMode::Capture, 4);
+
+        assert!(table.values.iter().all(|value| *value != UNSOLVED));
+    }
+
+    #[test]
+    fn solved_values_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for game_mode in GameMode::iter() {
+            let table = EndgameTable::build(game_mode, 7);
+            let mut seen = HashMap::new();
+
+            for _ in 0..500 {
+                let seeds = rng.gen_range(0..=table.max_seeds);
+                let position = random_position(&mut rng, seeds);
+
+                assert_eq!(
+                    table.get(&position),
+                    Some(brute_force(&position, game_mode, &mut seen)),
+                    "{game_mode} {position:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn best_move_keeps_the_value() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let table = EndgameTable::build(GameMode::Avalanche, 6);
+
+        for _ in 0..200 {
+            let position = random_position(&mut rng, 6);
+
+            let Some((_, score)) = table.best_move(&position) else {
+                assert!(position.is_over());
+                continue;
+            };
+
+            let value = table.get(&position).unwrap();
+
+            assert_eq!(
+                score,
+                super::super::evaluate(&position.counts, position.current) + value
+            );
+        }
+    }
+
+    #[test]
+    fn larger_positions_are_left_out() {
+        let table = EndgameTable::build(GameMode::Capture, 3);
+
+        assert_eq!(table.get(&Position::new(Player::One)), None);
+    }
+}
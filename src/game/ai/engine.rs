@@ -0,0 +1,187 @@
+//! Lets an external program play as the CPU, talking over its stdin and stdout one line at a time.
+//!
+//! The game sends:
+//! - `mancala <version>` right after launching the engine.
+//! - `position <MODE> <player> <c0> ... <c13>` with the mode (`AVALANCHE` or `CAPTURE`), the player
+//!   to move (`1` or `2`) and the count of every pit in board order, stores included.
+//! - `go <millis>` to ask for a move within the given time.
+//! - `quit` once the game is left.
+//!
+//! The engine answers `go` with `move <index>`, the board index of one of the mover's pits.
+//! Anything else it prints is ignored, so it's free to log. Running out of time, answering with an
+//! illegal move or crashing plays a random move instead.
+
+use super::agent::MancalaAgent;
+use crate::{game::rules::Position, profile::EngineConfig, states::GameMode};
+use bevy::prelude::*;
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex,
+    },
+    task::Poll,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Bumped whenever a command changes shape.
+pub const ENGINE_PROTOCOL_VERSION: u32 = 1;
+/// How long an engine gets to exit after `quit` before it's killed.
+const QUIT_GRACE: Duration = Duration::from_millis(100);
+/// How often an engine that was told to quit is checked on.
+const QUIT_POLL: Duration = Duration::from_millis(5);
+
+/// A running engine program.
+pub struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines read from the engine's stdout by a separate thread, so reading never blocks.
+    lines: Mutex<Receiver<String>>,
+    pub move_time: Duration,
+}
+
+impl EngineProcess {
+    pub fn launch(config: &EngineConfig) -> io::Result<Self> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let stdin = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        let stdout = child.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut process = Self {
+            child,
+            stdin,
+            lines: Mutex::new(receiver),
+            move_time: config.move_time(),
+        };
+
+        process.send(&format!("mancala {ENGINE_PROTOCOL_VERSION}"))?;
+
+        Ok(process)
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{line}")?;
+        self.stdin.flush()
+    }
+
+    /// Asks for a move in `position`. The answer comes back through `poll` or `wait`.
+    pub fn ask(&mut self, position: &Position, game_mode: GameMode) -> io::Result<()> {
+        let mut command = format!("position {game_mode} {}", position.current as usize + 1);
+
+        for count in position.counts {
+            command.push_str(&format!(" {count}"));
+        }
+
+        // anything left over answered an earlier question
+        self.poll();
+
+        self.send(&command)?;
+        self.send(&format!("go {}", self.move_time.as_millis()))
+    }
+
+    /// The move the engine answered with, if it has yet. It isn't checked for legality.
+    pub fn poll(&self) -> Option<usize> {
+        let lines = self.lines.lock().ok()?;
+        let answer = lines.try_iter().filter_map(|line| parse_move(&line)).last();
+
+        answer
+    }
+
+    /// Blocks until the engine answers, giving up once its move time has run out.
+    pub fn wait(&self) -> Option<usize> {
+        let deadline = Instant::now() + self.move_time;
+        let lines = self.lines.lock().ok()?;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            match lines.recv_timeout(remaining) {
+                Ok(line) => {
+                    if let Some(index) = parse_move(&line) {
+                        return Some(index);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        // ask nicely first, but don't leave a stuck engine running behind us
+        let _ = self.send("quit");
+
+        let deadline = Instant::now() + QUIT_GRACE;
+
+        while Instant::now() < deadline {
+            match self.child.try_wait() {
+                Ok(Some(_)) => return,
+                Ok(None) => thread::sleep(QUIT_POLL),
+                Err(_) => break,
+            }
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn parse_move(line: &str) -> Option<usize> {
+    line.strip_prefix("move ")?.trim().parse().ok()
+}
+
+/// An engine playing a CPU seat.
+pub struct EngineAgent {
+    process: EngineProcess,
+    /// When the move asked for is due.
+    deadline: Instant,
+}
+
+impl EngineAgent {
+    pub fn launch(config: &EngineConfig) -> io::Result<Self> {
+        EngineProcess::launch(config).map(|process| Self {
+            process,
+            deadline: Instant::now(),
+        })
+    }
+}
+
+impl MancalaAgent for EngineAgent {
+    fn think(&mut self, position: &Position, game_mode: GameMode) {
+        self.deadline = Instant::now() + self.process.move_time;
+
+        if let Err(error) = self.process.ask(position, game_mode) {
+            warn!("failed to reach engine: {error}");
+        }
+    }
+
+    fn poll(&mut self) -> Poll<Option<usize>> {
+        if let Some(index) = self.process.poll() {
+            return Poll::Ready(Some(index));
+        }
+
+        if Instant::now() >= self.deadline {
+            warn!("engine ran out of time");
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,190 @@
+//! Monte Carlo tree search: plays out random games from every move and keeps trying the moves that
+//! win most often, without knowing anything about mancala besides the rules.
+
+use super::{agent::MancalaAgent, Difficulty};
+use crate::{
+    game::{rules::Position, Player},
+    states::GameMode,
+};
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+use std::task::Poll;
+
+/// How much a rarely tried move is favoured over one that's been winning.
+const EXPLORATION: f64 = 1.4;
+
+struct Node {
+    position: Position,
+    parent: Option<usize>,
+    /// The move played to get here from the parent.
+    index: usize,
+    children: Vec<usize>,
+    untried: Vec<usize>,
+    visits: u32,
+    /// Won by whoever played `index`, with draws counting half.
+    wins: f64,
+}
+
+impl Node {
+    fn new(position: Position, parent: Option<usize>, index: usize) -> Self {
+        let untried = position.legal_moves().collect();
+
+        Self {
+            position,
+            parent,
+            index,
+            children: vec![],
+            untried,
+            visits: 0,
+            wins: 0.,
+        }
+    }
+}
+
+/// Plays moves at random until the game ends, returning the winner.
+fn playout(position: &Position, game_mode: GameMode, rng: &mut impl Rng) -> Option<Player> {
+    let mut position = position.clone();
+
+    while !position.is_over() {
+        let index = position
+            .legal_moves()
+            .choose(rng)
+            .expect("the game is over once a side has no moves");
+
+        position.play(index, game_mode);
+    }
+
+    position.sweep(game_mode);
+    position.leader()
+}
+
+/// The move tried most often after `iterations` playouts, or `None` if the game is over.
+pub fn search(
+    position: &Position,
+    game_mode: GameMode,
+    iterations: u32,
+    rng: &mut impl Rng,
+) -> Option<usize> {
+    if position.is_over() {
+        return None;
+    }
+
+    let mut nodes = vec![Node::new(position.clone(), None, 0)];
+
+    for _ in 0..iterations {
+        let mut current = 0;
+
+        // follow the most promising moves down to one with moves left to try
+        while nodes[current].untried.is_empty() && !nodes[current].children.is_empty() {
+            let parent_visits = f64::from(nodes[current].visits);
+
+            current = *nodes[current]
+                .children
+                .iter()
+                .max_by(|a, b| {
+                    let score = |child: &Node| {
+                        let visits = f64::from(child.visits);
+
+                        child.wins / visits + EXPLORATION * (parent_visits.ln() / visits).sqrt()
+                    };
+
+                    score(&nodes[**a]).total_cmp(&score(&nodes[**b]))
+                })
+                .expect("checked there are children");
+        }
+
+        if !nodes[current].position.is_over() && !nodes[current].untried.is_empty() {
+            let untried = &mut nodes[current].untried;
+            let index = untried.swap_remove(rng.gen_range(0..untried.len()));
+
+            let mut position = nodes[current].position.clone();
+            position.play(index, game_mode);
+
+            nodes.push(Node::new(position, Some(current), index));
+            let child = nodes.len() - 1;
+            nodes[current].children.push(child);
+            current = child;
+        }
+
+        let winner = playout(&nodes[current].position, game_mode, rng);
+
+        // each node counts the result for whoever moved into it
+        let mut node = Some(current);
+
+        while let Some(index) = node {
+            let parent = nodes[index].parent;
+
+            nodes[index].visits += 1;
+
+            if let Some(parent) = parent {
+                let mover = nodes[parent].position.current;
+
+                nodes[index].wins += match winner {
+                    Some(winner) if winner == mover => 1.,
+                    None => 0.5,
+                    Some(_) => 0.,
+                };
+            }
+
+            node = parent;
+        }
+    }
+
+    nodes[0]
+        .children
+        .iter()
+        .max_by_key(|child| nodes[**child].visits)
+        .map(|child| nodes[*child].index)
+}
+
+/// Searches in the background, playing out more games the harder it is.
+pub struct MctsAgent {
+    iterations: u32,
+    rng: StdRng,
+    task: Option<Task<Option<usize>>>,
+}
+
+impl MctsAgent {
+    pub fn new(difficulty: Difficulty, seed: u64) -> Self {
+        let iterations = match difficulty {
+            Difficulty::Easy => 200,
+            Difficulty::Medium => 2000,
+            Difficulty::Hard => 20000,
+            Difficulty::Perfect => 50000,
+        };
+
+        Self {
+            iterations,
+            rng: StdRng::seed_from_u64(seed),
+            task: None,
+        }
+    }
+}
+
+impl MancalaAgent for MctsAgent {
+    fn think(&mut self, position: &Position, game_mode: GameMode) {
+        let position = position.clone();
+        let iterations = self.iterations;
+        // each search gets its own generator, so it doesn't matter when the task gets to run
+        let mut rng = StdRng::seed_from_u64(self.rng.gen());
+
+        self.task = Some(
+            AsyncComputeTaskPool::get()
+                .spawn(async move { search(&position, game_mode, iterations, &mut rng) }),
+        );
+    }
+
+    fn poll(&mut self) -> Poll<Option<usize>> {
+        let Some(task) = &mut self.task else {
+            return Poll::Ready(None);
+        };
+
+        match block_on(future::poll_once(task)) {
+            Some(choice) => {
+                self.task = None;
+                Poll::Ready(choice)
+            }
+            None => Poll::Pending,
+        }
+    }
+}
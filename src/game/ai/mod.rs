@@ -0,0 +1,434 @@
+use super::{
+    board::SlotPressEvent,
+    clock::{Clock, TimeControl},
+    pause::PauseState,
+    resign::pending_offer,
+    rng::{GameRng, Stream},
+    rules::Position,
+    Board, CurrentPlayer, GameState, Player, Slot,
+};
+use crate::{
+    menu::{MenuState, Selected},
+    profile::{ProfileKind, Profiles},
+    states::{AppState, GameMode},
+};
+use agent::{AgentChoice, AgentRegistry, AgentSettings, Agents, MancalaAgent};
+use bevy::prelude::*;
+use bevy_persistent::Persistent;
+use book::OpeningBook;
+use endgame::{EndgameTable, Endgames};
+use engine::EngineAgent;
+use personality::Personality;
+use rand::{seq::IteratorRandom, Rng};
+use search::{Search, TranspositionTable};
+use std::{fmt, str::FromStr, task::Poll};
+use weights::TunedWeights;
+
+pub mod agent;
+pub mod book;
+pub mod endgame;
+pub mod engine;
+pub mod mcts;
+pub mod personality;
+pub mod search;
+pub mod weights;
+
+pub const AI_NAME: &str = "CPU";
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(endgame::EndgamePlugin)
+            .init_state::<AiState>()
+            .init_resource::<AiPlayers>()
+            .init_resource::<CpuDifficulty>()
+            .init_resource::<AgentRegistry>()
+            .init_resource::<AgentChoice>()
+            .init_resource::<Agents>()
+            .init_resource::<AiThinkTimer>()
+            .add_systems(OnEnter(AppState::Game), launch)
+            .add_systems(
+                Update,
+                selected_changed.run_if(in_state(MenuState::Profile)),
+            )
+            .add_systems(
+                OnEnter(GameState::Idle),
+                move_end.run_if(in_state(AiState::Idle)),
+            )
+            .add_systems(OnEnter(AiState::Thinking), think)
+            .add_systems(
+                Update,
+                play.run_if(in_state(AiState::Thinking))
+                    .run_if(in_state(PauseState::Running))
+                    .run_if(not(pending_offer)),
+            )
+            .add_systems(OnEnter(GameState::Over), stop)
+            .add_systems(OnExit(AppState::Game), (stop, quit));
+    }
+}
+
+/// How strongly the computer plays each seat, or `None` where a person plays. What plays it is up
+/// to `AgentChoice`.
+#[derive(Resource, Default)]
+pub struct AiPlayers(pub [Option<Difficulty>; 2]);
+
+impl AiPlayers {
+    pub fn controls(&self, player: Player) -> bool {
+        self.0[player as usize].is_some()
+    }
+
+    /// Whether the computer plays both seats, leaving everyone else to watch.
+    pub fn is_spectating(&self) -> bool {
+        Player::iter().all(|player| self.controls(player))
+    }
+
+    /// The seat of the only person playing, when the computer plays the other.
+    pub fn human(&self) -> Option<Player> {
+        let mut humans = Player::iter().filter(|player| !self.controls(*player));
+
+        match (humans.next(), humans.next()) {
+            (Some(player), None) => Some(player),
+            _ => None,
+        }
+    }
+}
+
+/// The difficulty picked for each seat, used whenever the computer plays it.
+#[derive(Resource, Default)]
+pub struct CpuDifficulty(pub [Difficulty; 2]);
+
+/// Holds a move back until a second into the turn, so it can be followed.
+#[derive(Resource)]
+struct AiThinkTimer(Timer);
+
+impl Default for AiThinkTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(1., TimerMode::Once))
+    }
+}
+
+#[derive(States, SystemSet, Debug, Hash, PartialEq, Eq, Clone, Default)]
+pub enum AiState {
+    #[default]
+    Inactive,
+    Idle,
+    Thinking,
+}
+
+/// How strongly the built-in CPU plays.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    /// Searches deepest, and plays out endgames perfectly from the endgame table.
+    Perfect,
+}
+
+impl Difficulty {
+    pub fn iter() -> impl Iterator<Item = Difficulty> {
+        [
+            Difficulty::Easy,
+            Difficulty::Medium,
+            Difficulty::Hard,
+            Difficulty::Perfect,
+        ]
+        .iter()
+        .copied()
+    }
+
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Easy => Self::Medium,
+            Self::Medium => Self::Hard,
+            Self::Hard => Self::Perfect,
+            Self::Perfect => Self::Easy,
+        }
+    }
+
+    /// How many moves ahead to search, or `None` to play at random.
+    pub const fn depth(self) -> Option<u32> {
+        match self {
+            Self::Easy => None,
+            Self::Medium => Some(3),
+            Self::Hard => Some(8),
+            Self::Perfect => Some(10),
+        }
+    }
+
+    /// Picks a move for the player to move, or `None` if the game is over, playing for what
+    /// `personality` likes if there is one. Only `Perfect` looks anything up in `endgame`.
+    pub fn choose(
+        self,
+        position: &Position,
+        game_mode: GameMode,
+        personality: Option<Personality>,
+        endgame: Option<&EndgameTable>,
+        table: &mut TranspositionTable,
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        // anything but random play opens from the book, unless it has tastes of its own
+        if self != Self::Easy && personality.is_none() {
+            if let Some(index) = OpeningBook::builtin().choose(position, game_mode, rng) {
+                return Some(index);
+            }
+        }
+
+        let endgame = endgame.filter(|_| self == Self::Perfect);
+        let weights = match personality {
+            Some(personality) => personality.weights(game_mode),
+            None => TunedWeights::builtin().get(game_mode),
+        };
+
+        match self.depth() {
+            Some(depth) => Search::with_weights(game_mode, weights, endgame, table)
+                .best_move(position, depth)
+                .map(|(index, _)| index),
+            None => position.legal_moves().choose(rng),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Easy => write!(f, "EASY"),
+            Self::Medium => write!(f, "MEDIUM"),
+            Self::Hard => write!(f, "HARD"),
+            Self::Perfect => write!(f, "PERFECT"),
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::iter()
+            .find(|difficulty| difficulty.to_string() == value)
+            .ok_or(())
+    }
+}
+
+/// Scores a position from `player`'s point of view, in seeds.
+pub fn evaluate(counts: &[u32], player: Player) -> i32 {
+    let own = counts[Board::get_store(player)] as i32;
+    let other = counts[Board::get_store(player.flip())] as i32;
+
+    own - other
+}
+
+/// The CPU takes a draw unless it expects to win from here, going by the endgame table when it
+/// has the position, and otherwise by searching as deep as it would to pick a move.
+pub fn accepts_draw(
+    position: &Position,
+    player: Player,
+    game_mode: GameMode,
+    difficulty: Difficulty,
+    endgame: Option<&EndgameTable>,
+) -> bool {
+    let score = match (
+        endgame.and_then(|endgame| endgame.get(position)),
+        difficulty.depth(),
+    ) {
+        (Some(value), _) => evaluate(&position.counts, position.current) + value,
+        (None, Some(depth)) => Search::new(game_mode, None, &mut TranspositionTable::new(1))
+            .negamax(position, depth, -i32::MAX, i32::MAX),
+        // random play doesn't look ahead, so it only sees the stores
+        (None, None) => evaluate(&position.counts, position.current),
+    };
+
+    if position.current == player {
+        score <= 0
+    } else {
+        score >= 0
+    }
+}
+
+fn selected_changed(
+    mut ai_state: ResMut<NextState<AiState>>,
+    mut ai_players: ResMut<AiPlayers>,
+    mut agent_choice: ResMut<AgentChoice>,
+    selected: Res<Selected>,
+    difficulty: Res<CpuDifficulty>,
+    profiles: Res<Persistent<Profiles>>,
+) {
+    if !selected.is_changed() && !difficulty.is_changed() {
+        return;
+    }
+
+    for (player, profile) in Player::iter().zip(selected.profiles) {
+        let profile = &profiles.0[profile];
+        let seat = player as usize;
+
+        ai_players.0[seat] = profile.is_ai().then_some(difficulty.0[seat]);
+        agent_choice.0[seat] = profile.is_ai().then(|| profile.kind.clone());
+    }
+
+    if Player::iter().any(|player| ai_players.controls(player)) {
+        ai_state.set(AiState::Idle);
+    } else {
+        ai_state.set(AiState::Inactive);
+    }
+}
+
+fn move_end(
+    mut ai_state: ResMut<NextState<AiState>>,
+    ai_players: Res<AiPlayers>,
+    current_player: Res<CurrentPlayer>,
+) {
+    if !ai_players.controls(current_player.0) {
+        return;
+    }
+
+    ai_state.set(AiState::Thinking);
+}
+
+fn stop(
+    ai_state: Res<State<AiState>>,
+    mut next_ai_state: ResMut<NextState<AiState>>,
+    mut ai_think_timer: ResMut<AiThinkTimer>,
+) {
+    if *ai_state.get() == AiState::Thinking {
+        next_ai_state.set(AiState::Idle);
+    }
+
+    ai_think_timer.0.reset();
+}
+
+/// Makes an agent for every CPU seat, falling back to the built-in CPU for anything that can't be
+/// made.
+fn launch(
+    mut commands: Commands,
+    ai_players: Res<AiPlayers>,
+    choice: Res<AgentChoice>,
+    registry: Res<AgentRegistry>,
+    endgames: Res<Endgames>,
+    game_mode: Res<State<GameMode>>,
+    mut rng: ResMut<GameRng>,
+) {
+    let agents = [Player::One, Player::Two].map(|player| {
+        let difficulty = ai_players.0[player as usize]?;
+        let kind = choice.0[player as usize].as_ref();
+        let settings = AgentSettings {
+            difficulty,
+            personality: match kind {
+                Some(ProfileKind::Personality(personality)) => Some(*personality),
+                _ => None,
+            },
+            endgame: endgames.get(*game_mode.get()),
+            seed: rng.stream(Stream::Ai).gen(),
+        };
+
+        let agent: Option<Box<dyn MancalaAgent>> = match kind {
+            Some(ProfileKind::Engine(config)) => EngineAgent::launch(config)
+                .inspect_err(|error| warn!("failed to launch engine `{}`: {error}", config.command))
+                .ok()
+                .map(|agent| Box::new(agent) as _),
+            Some(ProfileKind::Agent(name)) => registry.create(name, &settings).or_else(|| {
+                warn!("no agent called {name}");
+                None
+            }),
+            _ => None,
+        };
+
+        agent.or_else(|| registry.create(AI_NAME, &settings))
+    });
+
+    commands.insert_resource(Agents(agents));
+}
+
+fn think(
+    mut agents: ResMut<Agents>,
+    slot_query: Query<&Slot>,
+    current_player: Res<CurrentPlayer>,
+    game_mode: Res<State<GameMode>>,
+) {
+    let Some(agent) = &mut agents.0[current_player.0 as usize] else {
+        return;
+    };
+
+    let mut position = Position {
+        counts: [0; Board::LENGTH],
+        current: current_player.0,
+    };
+
+    for slot in &slot_query {
+        position.counts[slot.index] = slot.count;
+    }
+
+    agent.think(&position, *game_mode.get());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play(
+    mut agents: ResMut<Agents>,
+    slot_query: Query<&Slot>,
+    current_player: Res<CurrentPlayer>,
+    board: Res<Board>,
+    time: Res<Time>,
+    mut ai_state: ResMut<NextState<AiState>>,
+    mut ai_think_timer: ResMut<AiThinkTimer>,
+    mut slot_press_evw: EventWriter<SlotPressEvent>,
+    mut rng: ResMut<GameRng>,
+    time_control: Res<TimeControl>,
+    clock: Res<Clock>,
+) {
+    if !ai_think_timer.0.tick(time.delta()).finished() {
+        return;
+    }
+
+    let player = current_player.0;
+
+    let Some(agent) = &mut agents.0[player as usize] else {
+        return;
+    };
+
+    let out_of_time = matches!(*time_control, TimeControl::PerMove { .. })
+        && clock.remaining[player as usize].is_zero();
+
+    let choice = match agent.poll() {
+        Poll::Ready(choice) => choice,
+        // held to the move limit like a person, so a slow engine gets a random move too
+        Poll::Pending if out_of_time => {
+            warn!("agent ran out of time");
+            None
+        }
+        Poll::Pending => return,
+    };
+
+    let is_legal = |index: usize| {
+        Board::get_slots(player).contains(&index)
+            && slot_query
+                .get(board.slots[index])
+                .is_ok_and(|slot| slot.count > 0)
+    };
+
+    let index = match choice {
+        Some(index) if is_legal(index) => Some(index),
+        Some(index) => {
+            warn!("agent played illegal move {index}");
+            None
+        }
+        None => None,
+    }
+    .or_else(|| {
+        Board::get_slots(player)
+            .filter(|index| is_legal(*index))
+            .choose(rng.stream(Stream::Ai))
+    });
+
+    if let Some(index) = index {
+        slot_press_evw.send(SlotPressEvent(board.slots[index]));
+    }
+
+    ai_state.set(AiState::Idle);
+    ai_think_timer.0.reset();
+}
+
+fn quit(mut agents: ResMut<Agents>) {
+    // dropping an engine's agent closes it
+    *agents = Agents::default();
+}
@@ -0,0 +1,66 @@
+//! Named CPU opponents that each play for something besides the score, by weighing one feature of
+//! the board more than the tuned weights do.
+
+use super::weights::{Feature, TunedWeights, Weights, SCALE};
+use crate::states::GameMode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Personality {
+    /// Keeps as many seeds on its side as it can.
+    Hoarder,
+    /// Goes for captures, which only capture mode has, so it plays like the plain CPU in
+    /// avalanche.
+    Raider,
+    /// Lines up pits that end in its store, for extra turns.
+    Sprinter,
+}
+
+impl Personality {
+    pub fn iter() -> impl Iterator<Item = Personality> {
+        [
+            Personality::Hoarder,
+            Personality::Raider,
+            Personality::Sprinter,
+        ]
+        .iter()
+        .copied()
+    }
+
+    /// The feature played for, and how much more it's worth than the tuned weights say.
+    const fn favours(self) -> (Feature, i32) {
+        match self {
+            Self::Hoarder => (Feature::Side, SCALE),
+            Self::Raider => (Feature::Captures, SCALE * 2),
+            Self::Sprinter => (Feature::ExtraTurns, SCALE * 3),
+        }
+    }
+
+    pub fn weights(self, game_mode: GameMode) -> Weights {
+        let mut weights = TunedWeights::builtin().get(game_mode);
+        let (feature, extra) = self.favours();
+
+        weights.set(feature, weights.get(feature) + extra);
+
+        weights
+    }
+
+    pub const fn avatar(self) -> &'static str {
+        match self {
+            Self::Hoarder => "textures/hoarder.png",
+            Self::Raider => "textures/raider.png",
+            Self::Sprinter => "textures/sprinter.png",
+        }
+    }
+}
+
+impl fmt::Display for Personality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Hoarder => write!(f, "HOARDER"),
+            Self::Raider => write!(f, "RAIDER"),
+            Self::Sprinter => write!(f, "SPRINTER"),
+        }
+    }
+}
@@ -0,0 +1,495 @@
+use super::{
+    endgame::EndgameTable,
+    evaluate,
+    weights::{TunedWeights, Weights, SCALE},
+};
+use crate::{
+    game::{
+        rules::{Outcome, Position},
+        Board, Player, SLOT_START_AMOUNT,
+    },
+    states::GameMode,
+};
+use ahash::RandomState;
+use std::{mem, sync::OnceLock};
+
+/// How much memory a transposition table takes when no size is given.
+pub const DEFAULT_TABLE_MEGABYTES: usize = 8;
+
+/// Every seed on the board in one pit, the most any pit can hold in a real game.
+const KEYED_COUNTS: usize = 12 * SLOT_START_AMOUNT as usize + 1;
+
+/// Random keys XORed together to hash a position, so a move only has to touch the pits it changed.
+struct Zobrist {
+    counts: [[u64; KEYED_COUNTS]; Board::LENGTH],
+    player_two: u64,
+    /// Makes keys for counts past `KEYED_COUNTS`, which only made-up positions reach.
+    state: RandomState,
+}
+
+impl Zobrist {
+    fn get() -> &'static Self {
+        static ZOBRIST: OnceLock<Zobrist> = OnceLock::new();
+
+        ZOBRIST.get_or_init(|| {
+            // fixed seeds, so the same position always hashes the same
+            let state = RandomState::with_seeds(
+                0x243f_6a88_85a3_08d3,
+                0x1319_8a2e_0370_7344,
+                0xa409_3822_299f_31d0,
+                0x082e_fa98_ec4e_6c89,
+            );
+            let counts = std::array::from_fn(|index| {
+                std::array::from_fn(|count| state.hash_one((index, count)))
+            });
+
+            Self {
+                counts,
+                player_two: state.hash_one("player two"),
+                state,
+            }
+        })
+    }
+
+    fn count(&self, index: usize, count: u32) -> u64 {
+        match self.counts[index].get(count as usize) {
+            Some(key) => *key,
+            None => self.state.hash_one((index, count as usize)),
+        }
+    }
+
+    fn hash(&self, position: &Position) -> u64 {
+        let key = position
+            .counts
+            .iter()
+            .enumerate()
+            .fold(0, |key, (index, count)| key ^ self.count(index, *count));
+
+        match position.current {
+            Player::One => key,
+            Player::Two => key ^ self.player_two,
+        }
+    }
+
+    /// The hash of `after`, given the hash of `before` it was played from and what the move did,
+    /// so only the pits it sowed or captured get rehashed.
+    fn update(&self, key: u64, before: &Position, after: &Position, outcome: &Outcome) -> u64 {
+        let mut key = key;
+        let mut touched = 0u16;
+
+        for index in outcome.laps.iter().flatten() {
+            touched |= 1 << index;
+        }
+
+        if let Some(capture) = &outcome.capture {
+            for index in capture.pits.iter().chain([&capture.store]) {
+                touched |= 1 << index;
+            }
+        }
+
+        while touched != 0 {
+            let index = touched.trailing_zeros() as usize;
+            let (old, new) = (before.counts[index], after.counts[index]);
+
+            if old != new {
+                key ^= self.count(index, old) ^ self.count(index, new);
+            }
+
+            touched &= touched - 1;
+        }
+
+        if before.current != after.current {
+            key ^= self.player_two;
+        }
+
+        key
+    }
+}
+
+/// Which side of the true score a stored score is on, depending on whether it was cut short.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    /// The search stopped early on a move at least this good.
+    Lower,
+    /// No move reached the lower end of the window, so the score is at most this.
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    key: u64,
+    score: i32,
+    depth: u8,
+    bound: Bound,
+    /// The best move found, tried first next time.
+    best: u8,
+}
+
+/// Scores of positions already searched, in a fixed amount of memory. Newer entries push out
+/// older ones that land in the same place.
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    /// What the scores were searched under, since they don't carry over to other rules or weights.
+    context: Option<(GameMode, bool, Weights)>,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> Self {
+        let wanted = megabytes * 1024 * 1024 / mem::size_of::<Option<Entry>>();
+
+        // a power of two, so a hash picks its slot with a mask
+        let size = 1 << wanted.max(1).ilog2();
+
+        Self {
+            entries: vec![None; size],
+            context: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    /// Starts over if the scores so far were searched under different rules or weights.
+    fn prepare(&mut self, context: (GameMode, bool, Weights)) {
+        if self.context != Some(context) {
+            self.clear();
+            self.context = Some(context);
+        }
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+
+    fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries[self.slot(key)].filter(|entry| entry.key == key)
+    }
+
+    fn store(&mut self, entry: Entry) {
+        let slot = self.slot(entry.key);
+
+        self.entries[slot] = Some(entry);
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_MEGABYTES)
+    }
+}
+
+/// Alpha-beta search, where an extra turn counts as a move of its own. Positions in `endgame` are
+/// scored exactly however deep they are, and everything searched is remembered in `table`.
+///
+/// Scores come out in seeds, but inside the search they're in hundredths of a seed so the weights
+/// can tell apart positions with the same stores.
+pub struct Search<'a> {
+    game_mode: GameMode,
+    weights: Weights,
+    endgame: Option<&'a EndgameTable>,
+    table: &'a mut TranspositionTable,
+    zobrist: &'static Zobrist,
+}
+
+impl<'a> Search<'a> {
+    /// Searches with the built-in weights for `game_mode`.
+    pub fn new(
+        game_mode: GameMode,
+        endgame: Option<&'a EndgameTable>,
+        table: &'a mut TranspositionTable,
+    ) -> Self {
+        let weights = TunedWeights::builtin().get(game_mode);
+
+        Self::with_weights(game_mode, weights, endgame, table)
+    }
+
+    pub fn with_weights(
+        game_mode: GameMode,
+        weights: Weights,
+        endgame: Option<&'a EndgameTable>,
+        table: &'a mut TranspositionTable,
+    ) -> Self {
+        table.prepare((game_mode, endgame.is_some(), weights));
+
+        Self {
+            game_mode,
+            weights,
+            endgame,
+            table,
+            zobrist: Zobrist::get(),
+        }
+    }
+
+    /// Scores `position` for the player to move, looking `depth` moves ahead.
+    pub fn negamax(&mut self, position: &Position, depth: u32, alpha: i32, beta: i32) -> i32 {
+        let key = self.zobrist.hash(position);
+
+        to_seeds(self.search(position, key, depth, from_seeds(alpha), from_seeds(beta)))
+    }
+
+    /// Scores playing `index` for the player to move.
+    pub fn score_move(
+        &mut self,
+        position: &Position,
+        index: usize,
+        depth: u32,
+        alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        let key = self.zobrist.hash(position);
+        let (alpha, beta) = (from_seeds(alpha), from_seeds(beta));
+
+        to_seeds(self.score_child(position, key, index, depth, alpha, beta))
+    }
+
+    /// The best move for the player to move and its score, or `None` if the game is over.
+    pub fn best_move(&mut self, position: &Position, depth: u32) -> Option<(usize, i32)> {
+        if position.is_over() {
+            return None;
+        }
+
+        if let Some(best) = self.endgame.and_then(|endgame| endgame.best_move(position)) {
+            return Some(best);
+        }
+
+        let key = self.zobrist.hash(position);
+        let depth = depth.max(1);
+        let first = self.table.probe(key).map(|entry| entry.best);
+        let mut best = None;
+        let mut alpha = -i32::MAX;
+
+        for index in ordered_moves(position, first) {
+            let score = self.score_child(position, key, index, depth, alpha, i32::MAX);
+
+            if best.is_none_or(|(_, best)| score > best) {
+                best = Some((index, score));
+                alpha = alpha.max(score);
+            }
+        }
+
+        best.map(|(index, score)| (index, to_seeds(score)))
+    }
+
+    /// Flips the point of view when the turn passes.
+    fn score_child(
+        &mut self,
+        position: &Position,
+        key: u64,
+        index: usize,
+        depth: u32,
+        alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        let mut child = position.clone();
+        let outcome = child.play(index, self.game_mode);
+        let key = self.zobrist.update(key, position, &child, &outcome);
+
+        if child.current == position.current {
+            self.search(&child, key, depth - 1, alpha, beta)
+        } else {
+            -self.search(&child, key, depth - 1, -beta, -alpha)
+        }
+    }
+
+    fn search(&mut self, position: &Position, key: u64, depth: u32, alpha: i32, beta: i32) -> i32 {
+        if let Some(value) = self.endgame.and_then(|endgame| endgame.get(position)) {
+            return (evaluate(&position.counts, position.current) + value) * SCALE;
+        }
+
+        if position.is_over() {
+            let mut position = position.clone();
+            position.sweep(self.game_mode);
+
+            return evaluate(&position.counts, position.current) * SCALE;
+        }
+
+        if depth == 0 {
+            return self.weights.score(position, self.game_mode);
+        }
+
+        let (mut alpha, mut beta) = (alpha, beta);
+        let original_alpha = alpha;
+        let entry = self.table.probe(key);
+
+        if let Some(entry) = entry {
+            if u32::from(entry.depth) >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.score,
+                    Bound::Lower => alpha = alpha.max(entry.score),
+                    Bound::Upper => beta = beta.min(entry.score),
+                }
+
+                if alpha >= beta {
+                    return entry.score;
+                }
+            }
+        }
+
+        let mut best = -i32::MAX;
+        let mut best_index = 0;
+
+        for index in ordered_moves(position, entry.map(|entry| entry.best)) {
+            let score = self.score_child(position, key, index, depth, alpha, beta);
+
+            if score > best {
+                best = score;
+                best_index = index;
+            }
+
+            alpha = alpha.max(score);
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+
+        self.table.store(Entry {
+            key,
+            score: best,
+            depth: depth.min(u8::MAX.into()) as u8,
+            bound,
+            best: best_index as u8,
+        });
+
+        best
+    }
+}
+
+/// A window given in seeds, kept clear of `i32::MIN` so it can still be negated.
+fn from_seeds(score: i32) -> i32 {
+    score.saturating_mul(SCALE).max(-i32::MAX)
+}
+
+/// Rounds a score from inside the search to the nearest seed.
+fn to_seeds(score: i32) -> i32 {
+    score.saturating_add(score.signum() * SCALE / 2) / SCALE
+}
+
+/// Legal moves, starting with `first` if it's one of them, which was best the last time the
+/// position was searched.
+fn ordered_moves(position: &Position, first: Option<u8>) -> impl Iterator<Item = usize> + '_ {
+    let first = first
+        .map(usize::from)
+        .filter(|index| position.is_legal(*index));
+
+    first.into_iter().chain(
+        position
+            .legal_moves()
+            .filter(move |index| Some(*index) != first),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// The same search with nothing remembered, in hundredths of a seed.
+    fn plain(position: &Position, depth: u32, game_mode: GameMode, weights: &Weights) -> i32 {
+        if position.is_over() {
+            let mut position = position.clone();
+            position.sweep(game_mode);
+
+            return evaluate(&position.counts, position.current) * SCALE;
+        }
+
+        if depth == 0 {
+            return weights.score(position, game_mode);
+        }
+
+        position
+            .legal_moves()
+            .map(|index| {
+                let mut child = position.clone();
+                child.play(index, game_mode);
+
+                let score = plain(&child, depth - 1, game_mode, weights);
+
+                if child.current == position.current {
+                    score
+                } else {
+                    -score
+                }
+            })
+            .max()
+            .unwrap()
+    }
+
+    /// Positions from random games, some way in.
+    fn positions(game_mode: GameMode, rng: &mut StdRng) -> Vec<Position> {
+        (0..40)
+            .map(|_| {
+                let mut position = Position::new(Player::One);
+
+                for _ in 0..rng.gen_range(0..20) {
+                    let legal: Vec<usize> = position.legal_moves().collect();
+
+                    if legal.is_empty() {
+                        break;
+                    }
+
+                    position.play(legal[rng.gen_range(0..legal.len())], game_mode);
+                }
+
+                position
+            })
+            .collect()
+    }
+
+    #[test]
+    fn updated_keys_match_fresh_hashes() {
+        let zobrist = Zobrist::get();
+        let mut rng = StdRng::seed_from_u64(3);
+
+        for game_mode in GameMode::iter() {
+            for position in positions(game_mode, &mut rng) {
+                let key = zobrist.hash(&position);
+
+                for index in position.legal_moves() {
+                    let mut child = position.clone();
+                    let outcome = child.play(index, game_mode);
+
+                    assert_eq!(
+                        zobrist.update(key, &position, &child, &outcome),
+                        zobrist.hash(&child),
+                        "{game_mode} {position:?} {index}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_table_keeps_the_score() {
+        let mut rng = StdRng::seed_from_u64(11);
+
+        for game_mode in GameMode::iter() {
+            let weights = TunedWeights::builtin().get(game_mode);
+
+            for position in positions(game_mode, &mut rng) {
+                // deepening like the CPUs do, so later searches start from what earlier ones found
+                let mut table = TranspositionTable::new(1);
+
+                for depth in 1..=5 {
+                    let mut search = Search::new(game_mode, None, &mut table);
+
+                    assert_eq!(
+                        search.negamax(&position, depth, -i32::MAX, i32::MAX),
+                        to_seeds(plain(&position, depth, game_mode, &weights)),
+                        "{game_mode} depth {depth} {position:?}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! How the search scores a position it can't see past, as a sum of weighted features of the board.
+//!
+//! Weights are in hundredths of a seed. A weights file starts with the line `MANCALA WEIGHTS 1`,
+//! followed by a `<MODE> <FEATURE> <weight>` line for every weight, with the mode (`AVALANCHE` or
+//! `CAPTURE`) and the feature (`STORE`, `SIDE`, `MOBILITY`, `CAPTURES` or `EXTRA_TURNS`). Any
+//! weight left out keeps its default, which only counts the stores.
+//!
+//! `cargo run --release --bin tune` tunes new weights through self-play and writes them to
+//! `assets/weights.txt`, which is built in.
+
+use crate::{
+    game::{rules::Position, Board, Player},
+    states::GameMode,
+};
+use bevy::log::warn;
+use std::{collections::HashMap, fmt, str::FromStr, sync::OnceLock};
+
+/// A seed is worth this much to the search, so weights can be finer than a whole seed.
+pub const SCALE: i32 = 100;
+
+const HEADER: &str = "MANCALA WEIGHTS 1";
+
+/// Something about one player's side of the board, counted the same way for both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// Seeds in the store.
+    Store,
+    /// Seeds still in the pits.
+    Side,
+    /// Pits that can be played.
+    Mobility,
+    /// The most seeds a single move would capture, in capture mode.
+    Captures,
+    /// Pits that would end in the store and earn an extra turn.
+    ExtraTurns,
+}
+
+impl Feature {
+    pub const COUNT: usize = 5;
+
+    pub fn iter() -> impl Iterator<Item = Feature> {
+        [
+            Feature::Store,
+            Feature::Side,
+            Feature::Mobility,
+            Feature::Captures,
+            Feature::ExtraTurns,
+        ]
+        .iter()
+        .copied()
+    }
+
+    fn measure(self, position: &Position, player: Player, game_mode: GameMode) -> i32 {
+        let counts = &position.counts;
+        let store = Board::get_store(player);
+
+        match self {
+            Self::Store => counts[store] as i32,
+            Self::Side => Board::get_slots(player)
+                .map(|index| counts[index] as i32)
+                .sum(),
+            Self::Mobility => Board::get_slots(player)
+                .filter(|index| counts[*index] > 0)
+                .count() as i32,
+            Self::Captures if game_mode == GameMode::Capture => Board::get_slots(player)
+                .filter_map(|index| {
+                    let target = landing(index, counts[index], player)
+                        .filter(|target| Board::owner(*target) == player && *target != store)?;
+                    let opposite = counts[Board::LENGTH - target - 2];
+
+                    (counts[target] == 0 && opposite > 0).then_some(opposite as i32 + 1)
+                })
+                .max()
+                .unwrap_or(0),
+            Self::Captures => 0,
+            Self::ExtraTurns => Board::get_slots(player)
+                .filter(|index| landing(*index, counts[*index], player) == Some(store))
+                .count() as i32,
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Store => write!(f, "STORE"),
+            Self::Side => write!(f, "SIDE"),
+            Self::Mobility => write!(f, "MOBILITY"),
+            Self::Captures => write!(f, "CAPTURES"),
+            Self::ExtraTurns => write!(f, "EXTRA_TURNS"),
+        }
+    }
+}
+
+impl FromStr for Feature {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::iter()
+            .find(|feature| feature.to_string() == value)
+            .ok_or(())
+    }
+}
+
+/// Where the last of `seeds` sown from `index` lands, if they don't go all the way round.
+fn landing(index: usize, seeds: u32, player: Player) -> Option<usize> {
+    if seeds == 0 || seeds >= Board::LENGTH as u32 - 1 {
+        return None;
+    }
+
+    let mut index = index;
+
+    for _ in 0..seeds {
+        index = (index + 1) % Board::LENGTH;
+
+        if index == Board::get_store(player.flip()) {
+            index = (index + 1) % Board::LENGTH;
+        }
+    }
+
+    Some(index)
+}
+
+/// What each feature is worth, in hundredths of a seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Weights(pub [i32; Feature::COUNT]);
+
+impl Weights {
+    pub fn get(&self, feature: Feature) -> i32 {
+        self.0[feature as usize]
+    }
+
+    pub fn set(&mut self, feature: Feature, weight: i32) {
+        self.0[feature as usize] = weight;
+    }
+
+    /// Scores `position` for the player to move, in hundredths of a seed.
+    pub fn score(&self, position: &Position, game_mode: GameMode) -> i32 {
+        let player = position.current;
+
+        Feature::iter()
+            .filter(|feature| self.get(*feature) != 0)
+            .map(|feature| {
+                let own = feature.measure(position, player, game_mode);
+                let other = feature.measure(position, player.flip(), game_mode);
+
+                self.get(feature) * (own - other)
+            })
+            .sum()
+    }
+}
+
+impl Default for Weights {
+    /// Only the stores count, a seed each.
+    fn default() -> Self {
+        let mut weights = Self([0; Feature::COUNT]);
+        weights.set(Feature::Store, SCALE);
+
+        weights
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WeightsError {
+    Malformed(usize),
+    /// The line names a mode this build doesn't know.
+    UnknownMode(usize),
+    /// The line names a feature this build doesn't know.
+    UnknownFeature(usize),
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed(line) => write!(f, "malformed line {}", line + 1),
+            Self::UnknownMode(line) => write!(f, "unknown mode on line {}", line + 1),
+            Self::UnknownFeature(line) => write!(f, "unknown feature on line {}", line + 1),
+        }
+    }
+}
+
+/// The weights for each game mode.
+#[derive(Debug, Clone, Default)]
+pub struct TunedWeights {
+    modes: HashMap<GameMode, Weights>,
+}
+
+impl TunedWeights {
+    /// The weights shipped with the game.
+    pub fn builtin() -> &'static Self {
+        static WEIGHTS: OnceLock<TunedWeights> = OnceLock::new();
+
+        WEIGHTS.get_or_init(|| {
+            include_str!("../../../assets/weights.txt")
+                .parse()
+                .inspect_err(|error| warn!("failed to read evaluation weights: {error}"))
+                .unwrap_or_default()
+        })
+    }
+
+    /// The weights for `game_mode`, or the defaults if there aren't any.
+    pub fn get(&self, game_mode: GameMode) -> Weights {
+        self.modes.get(&game_mode).copied().unwrap_or_default()
+    }
+
+    pub fn insert(&mut self, game_mode: GameMode, weights: Weights) {
+        self.modes.insert(game_mode, weights);
+    }
+}
+
+impl fmt::Display for TunedWeights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+
+        for game_mode in GameMode::iter() {
+            let Some(weights) = self.modes.get(&game_mode) else {
+                continue;
+            };
+
+            for feature in Feature::iter() {
+                writeln!(f, "{game_mode} {feature} {}", weights.get(feature))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for TunedWeights {
+    type Err = WeightsError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+
+        if lines.next() != Some(HEADER) {
+            return Err(WeightsError::Malformed(0));
+        }
+
+        let mut weights = Self::default();
+
+        for (number, line) in lines.enumerate().map(|(number, line)| (number + 1, line)) {
+            if line.is_empty() {
+                continue;
+            }
+
+            let [game_mode, feature, weight] = line
+                .split(' ')
+                .collect::<Vec<_>>()
+                .try_into()
+                .map_err(|_| WeightsError::Malformed(number))?;
+
+            let game_mode: GameMode = game_mode
+                .parse()
+                .map_err(|_| WeightsError::UnknownMode(number))?;
+            let feature: Feature = feature
+                .parse()
+                .map_err(|_| WeightsError::UnknownFeature(number))?;
+            let weight = weight
+                .parse()
+                .map_err(|_| WeightsError::Malformed(number))?;
+
+            weights
+                .modes
+                .entry(game_mode)
+                .or_default()
+                .set(feature, weight);
+        }
+
+        Ok(weights)
+    }
+}
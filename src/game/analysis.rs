@@ -0,0 +1,433 @@
+use super::{
+    ai::search::{Search, TranspositionTable},
+    game_over::GameOverState,
+    marble::{self, MarbleEvent, MarbleOutlineEvent, MarbleStack},
+    rules::Position,
+    Board, GameRecord, GameState, MoveRecord, Player, Slot,
+};
+use crate::{states::GameMode, ui::UiAssets};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+use std::fmt;
+
+/// Looks as far ahead as the Perfect CPU, but without its endgame table, since every move only
+/// needs judging once.
+const ANALYSIS_DEPTH: u32 = 10;
+
+pub struct AnalysisPlugin;
+
+impl Plugin for AnalysisPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameOverState::Analysing), start)
+            .add_systems(
+                Update,
+                (
+                    finish.run_if(resource_exists::<AnalysisTask>),
+                    (button_action, show_moment, keep_outline).run_if(resource_exists::<Analysis>),
+                )
+                    .chain()
+                    .run_if(in_state(GameOverState::Analysing)),
+            )
+            .add_systems(OnExit(GameOverState::Analysing), end)
+            .add_systems(OnExit(GameState::Over), end);
+    }
+}
+
+/// How much a move gave away compared to the best one, in seeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Judgement {
+    Best,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    pub const fn from_loss(loss: i32) -> Self {
+        match loss {
+            i32::MIN..=0 => Self::Best,
+            1..=2 => Self::Inaccuracy,
+            3..=5 => Self::Mistake,
+            _ => Self::Blunder,
+        }
+    }
+}
+
+impl fmt::Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Best => write!(f, "BEST"),
+            Self::Inaccuracy => write!(f, "INACCURACY"),
+            Self::Mistake => write!(f, "MISTAKE"),
+            Self::Blunder => write!(f, "BLUNDER"),
+        }
+    }
+}
+
+/// One move of the game, next to what the search would have played instead.
+#[derive(Debug, Clone)]
+pub struct MoveAnalysis {
+    /// The position the move was played from.
+    pub position: Position,
+    pub played: usize,
+    pub best: usize,
+    /// Seeds given away by not playing `best`.
+    pub loss: i32,
+    pub judgement: Judgement,
+}
+
+/// Judges every move of a game, replayed from the start.
+pub fn analyse(moves: &[MoveRecord], game_mode: GameMode, depth: u32) -> Vec<MoveAnalysis> {
+    let Some(first) = moves.first() else {
+        return vec![];
+    };
+
+    let mut position = Position::new(first.player);
+    let mut analysis = Vec::with_capacity(moves.len());
+    let mut table = TranspositionTable::default();
+    let mut search = Search::new(game_mode, None, &mut table);
+
+    for record in moves {
+        if !position.is_legal(record.index) {
+            break;
+        }
+
+        let Some((best, best_score)) = search.best_move(&position, depth) else {
+            break;
+        };
+
+        let played_score = search.score_move(&position, record.index, depth, -i32::MAX, i32::MAX);
+        let loss = (best_score - played_score).max(0);
+
+        analysis.push(MoveAnalysis {
+            position: position.clone(),
+            played: record.index,
+            best,
+            loss,
+            judgement: Judgement::from_loss(loss),
+        });
+
+        position.play(record.index, game_mode);
+    }
+
+    analysis
+}
+
+#[derive(Resource)]
+struct AnalysisTask(Task<Vec<MoveAnalysis>>);
+
+/// A finished analysis, stepped through one flagged move at a time.
+#[derive(Resource)]
+struct Analysis {
+    moves: Vec<MoveAnalysis>,
+    /// Indices into `moves` of everything worse than the best move.
+    flagged: Vec<usize>,
+    /// Which of `flagged` is on the board.
+    cursor: usize,
+    /// The board as the game ended, put back afterwards.
+    final_counts: Vec<u32>,
+}
+
+#[derive(Component)]
+struct AnalysisScreen;
+
+#[derive(Component)]
+struct AnalysisText;
+
+#[derive(Component, Clone, Copy)]
+enum AnalysisButtonAction {
+    Previous,
+    Next,
+    Back,
+}
+
+impl fmt::Display for AnalysisButtonAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Previous => write!(f, "PREV"),
+            Self::Next => write!(f, "NEXT"),
+            Self::Back => write!(f, "BACK"),
+        }
+    }
+}
+
+/// Names a pit the way its owner sees it, counting from 1.
+fn pit_name(index: usize) -> String {
+    let slots = Board::get_slots(Board::owner(index));
+
+    format!("PIT {}", index - slots.start + 1)
+}
+
+fn summary(moves: &[MoveAnalysis]) -> String {
+    Player::iter()
+        .map(|player| {
+            let count = |judgement: Judgement| {
+                moves
+                    .iter()
+                    .filter(|analysis| {
+                        analysis.position.current == player && analysis.judgement == judgement
+                    })
+                    .count()
+            };
+
+            format!(
+                "{player}: {} INACCURACIES, {} MISTAKES, {} BLUNDERS",
+                count(Judgement::Inaccuracy),
+                count(Judgement::Mistake),
+                count(Judgement::Blunder)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn start(
+    mut commands: Commands,
+    record: Res<GameRecord>,
+    game_mode: Res<State<GameMode>>,
+    ui_assets: Res<UiAssets>,
+) {
+    let moves = record.moves.clone();
+    let game_mode = *game_mode.get();
+    let task = AsyncComputeTaskPool::get()
+        .spawn(async move { analyse(&moves, game_mode, ANALYSIS_DEPTH) });
+
+    commands.insert_resource(AnalysisTask(task));
+
+    let style = TextStyle {
+        font: ui_assets.font.clone(),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    bottom: Val::Px(20.),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.),
+                    ..default()
+                },
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            AnalysisScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("ANALYSING...", style.clone())
+                    .with_text_justify(JustifyText::Center),
+                AnalysisText,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        display: Display::Flex,
+                        column_gap: Val::Px(40.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for action in [
+                        AnalysisButtonAction::Previous,
+                        AnalysisButtonAction::Next,
+                        AnalysisButtonAction::Back,
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    background_color: Color::NONE.into(),
+                                    ..default()
+                                },
+                                action,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    action.to_string(),
+                                    style.clone(),
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+fn finish(mut commands: Commands, mut task: ResMut<AnalysisTask>, slot_query: Query<&Slot>) {
+    let Some(moves) = block_on(future::poll_once(&mut task.0)) else {
+        return;
+    };
+
+    let mut final_counts = vec![0; Board::LENGTH];
+
+    for slot in &slot_query {
+        final_counts[slot.index] = slot.count;
+    }
+
+    let flagged = moves
+        .iter()
+        .enumerate()
+        .filter(|(_, analysis)| analysis.judgement != Judgement::Best)
+        .map(|(index, _)| index)
+        .collect();
+
+    commands.remove_resource::<AnalysisTask>();
+    commands.insert_resource(Analysis {
+        moves,
+        flagged,
+        cursor: 0,
+        final_counts,
+    });
+}
+
+#[allow(clippy::type_complexity)]
+fn button_action(
+    interaction_query: Query<
+        (&Children, &Interaction, &AnalysisButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut analysis: ResMut<Analysis>,
+    mut state: ResMut<NextState<GameOverState>>,
+) {
+    for (children, interaction, action) in &interaction_query {
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+
+        match interaction {
+            Interaction::Pressed => {
+                let count = analysis.flagged.len().max(1);
+
+                match *action {
+                    AnalysisButtonAction::Previous => {
+                        analysis.cursor = (analysis.cursor + count - 1) % count;
+                    }
+                    AnalysisButtonAction::Next => {
+                        analysis.cursor = (analysis.cursor + 1) % count;
+                    }
+                    AnalysisButtonAction::Back => {
+                        state.set(GameOverState::Visible);
+                    }
+                }
+
+                // still hovered, so keep the arrows
+                text.sections[0].value = format!("> {action} <");
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = format!("> {action} <");
+            }
+            Interaction::None => {
+                text.sections[0].value = action.to_string();
+            }
+        }
+    }
+}
+
+/// Sets the board up as it was before the flagged move, and explains what went wrong.
+fn show_moment(
+    analysis: Res<Analysis>,
+    mut text_query: Query<&mut Text, With<AnalysisText>>,
+    mut slot_query: Query<(Entity, &mut Slot)>,
+    mut marble_events: EventWriter<MarbleEvent>,
+    mut marble_outline_events: EventWriter<MarbleOutlineEvent>,
+    stack_query: Query<&MarbleStack>,
+    board: Res<Board>,
+) {
+    if !analysis.is_changed() {
+        return;
+    }
+
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+
+    let summary = summary(&analysis.moves);
+
+    let Some(&number) = analysis.flagged.get(analysis.cursor) else {
+        text.sections[0].value = format!("{summary}\nNO MISTAKES FOUND");
+        return;
+    };
+
+    let moment = &analysis.moves[number];
+
+    // the previous moment's outline would otherwise stay on
+    for slot in &board.slots {
+        marble_outline_events.send(MarbleOutlineEvent(*slot, Visibility::Hidden));
+    }
+
+    marble::restack(
+        &moment.position.counts,
+        &mut slot_query,
+        &stack_query,
+        &mut marble_events,
+    );
+
+    text.sections[0].value = format!(
+        "{summary}\n{} OF {}: MOVE {}, {} PLAYED {}\n{}, BEST WAS {} (-{} SEEDS)",
+        analysis.cursor + 1,
+        analysis.flagged.len(),
+        number + 1,
+        moment.position.current,
+        pit_name(moment.played),
+        moment.judgement,
+        pit_name(moment.best),
+        moment.loss
+    );
+}
+
+/// Restacked marbles come in without an outline, so the better move is outlined again every frame.
+fn keep_outline(
+    analysis: Res<Analysis>,
+    mut marble_outline_events: EventWriter<MarbleOutlineEvent>,
+    board: Res<Board>,
+) {
+    let Some(&number) = analysis.flagged.get(analysis.cursor) else {
+        return;
+    };
+
+    marble_outline_events.send(MarbleOutlineEvent(
+        board.slots[analysis.moves[number].best],
+        Visibility::Visible,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn end(
+    mut commands: Commands,
+    analysis: Option<Res<Analysis>>,
+    screen_query: Query<Entity, With<AnalysisScreen>>,
+    mut slot_query: Query<(Entity, &mut Slot)>,
+    mut marble_events: EventWriter<MarbleEvent>,
+    mut marble_outline_events: EventWriter<MarbleOutlineEvent>,
+    stack_query: Query<&MarbleStack>,
+    board: Res<Board>,
+) {
+    if let Some(analysis) = analysis {
+        marble::restack(
+            &analysis.final_counts,
+            &mut slot_query,
+            &stack_query,
+            &mut marble_events,
+        );
+    }
+
+    for slot in &board.slots {
+        marble_outline_events.send(MarbleOutlineEvent(*slot, Visibility::Hidden));
+    }
+
+    for entity in &screen_query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands.remove_resource::<AnalysisTask>();
+    commands.remove_resource::<Analysis>();
+}
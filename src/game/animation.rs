@@ -1,5 +1,8 @@
 use super::{
+    helpers,
     marble::{MarbleEvent, MarbleEventKind, MarbleOutlineEvent, MarbleStack, MarbleStackEntity},
+    pause::halted,
+    rng::{GameRng, Stream},
     Board, CaptureEvent, MoveEvent, Slot,
 };
 use crate::states::AppState;
@@ -30,7 +33,16 @@ impl Plugin for AnimationPlugin {
                     .chain()
                     .run_if(in_state(AppState::Game)),
             )
-            .add_systems(FixedUpdate, animation_tick.run_if(in_state(AppState::Game)));
+            .add_systems(
+                FixedUpdate,
+                animation_tick
+                    .run_if(not(halted))
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                OnExit(AppState::Game),
+                (clear_queue, helpers::despawn::<Stack>),
+            );
     }
 }
 
@@ -66,6 +78,7 @@ impl Animation for MoveAnimation {
         self.started = true;
     }
 
+    #[allow(clippy::type_complexity)]
     fn tick(&mut self, world: &mut World) {
         let mut system_state: SystemState<(
             EventWriter<MarbleEvent>,
@@ -165,17 +178,19 @@ impl Animation for CaptureAnimation {
 
             let translation: Vec2 = world.get::<Transform>(*container).unwrap().translation.xy();
 
-            let mut rng = rand::thread_rng();
-
-            for child in &mut children {
-                let offset = (
-                    rng.gen_range(-CAPTURE_OFFSET_X..=CAPTURE_OFFSET_X),
-                    rng.gen_range(-CAPTURE_OFFSET_Y..=CAPTURE_OFFSET_Y),
-                );
-                world
-                    .entity_mut(*child)
-                    .insert(Offset(Vec2::new(-translation.x + offset.0, offset.1)));
-            }
+            world.resource_scope(|world, mut rng: Mut<GameRng>| {
+                let rng = rng.stream(Stream::Captures);
+
+                for child in &mut children {
+                    let offset = (
+                        rng.gen_range(-CAPTURE_OFFSET_X..=CAPTURE_OFFSET_X),
+                        rng.gen_range(-CAPTURE_OFFSET_Y..=CAPTURE_OFFSET_Y),
+                    );
+                    world
+                        .entity_mut(*child)
+                        .insert(Offset(Vec2::new(-translation.x + offset.0, offset.1)));
+                }
+            });
 
             world.entity_mut(*container).push_children(&children);
         }
@@ -183,6 +198,7 @@ impl Animation for CaptureAnimation {
         self.started = true;
     }
 
+    #[allow(clippy::type_complexity)]
     fn tick(&mut self, world: &mut World) {
         let mut system_state: SystemState<(
             EventWriter<MarbleEvent>,
@@ -294,6 +310,12 @@ fn update_state(mut state: ResMut<NextState<AnimationState>>, queue: Res<Animati
     }
 }
 
+fn clear_queue(mut state: ResMut<NextState<AnimationState>>, mut queue: ResMut<AnimationQueue>) {
+    // animations hold entities that are about to be despawned, so they can't outlive the game
+    queue.0.clear();
+    state.set(AnimationState::Idle);
+}
+
 fn animation_tick(world: &mut World) {
     world.resource_scope(|world, mut queue: Mut<AnimationQueue>| {
         let Some((animation, timer)) = queue.0.front_mut() else {
@@ -328,6 +350,7 @@ pub fn handle_move(
     transform_query: Query<&Transform>,
     slot_query: Query<&Slot>,
     mut animations: ResMut<AnimationQueue>,
+    mut rng: ResMut<GameRng>,
 ) {
     for MoveEvent(moves) in move_events.read() {
         let mut slots = moves.clone();
@@ -335,13 +358,12 @@ pub fn handle_move(
 
         let mut queue: VecDeque<(Entity, Vec2)> = VecDeque::new();
 
-        let mut rng = rand::thread_rng();
-
         for slot in slots {
             if let Some((entity, _)) = marble_stack.get(slot) {
                 if let Ok(component) = slot_query.get(slot) {
                     let offset = if Board::is_store(component.index) {
-                        rng.gen_range(-MOVE_STORE_OFFSET..=MOVE_STORE_OFFSET)
+                        rng.stream(Stream::Moves)
+                            .gen_range(-MOVE_STORE_OFFSET..=MOVE_STORE_OFFSET)
                     } else {
                         match component.index % 2 {
                             0 => MOVE_SLOT_OFFSET,
@@ -413,10 +435,13 @@ fn handle_capture(
         for slot in &event.slots {
             if let Some((stack_container, stack)) = marble_stack.get(*slot) {
                 let container = commands
-                    .spawn((SpatialBundle {
-                        transform: Transform::from_translation(stack.1.extend(100.)),
-                        ..default()
-                    },))
+                    .spawn((
+                        SpatialBundle {
+                            transform: Transform::from_translation(stack.1.extend(100.)),
+                            ..default()
+                        },
+                        Stack,
+                    ))
                     .id();
 
                 commands
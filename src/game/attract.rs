@@ -0,0 +1,210 @@
+use super::{
+    ai::{agent::AgentChoice, AiPlayers, AiState, Difficulty, AI_NAME},
+    clock::TimeControl,
+    helpers,
+    rng::{GameRng, Stream},
+    turn_indicator::TurnIndicatorLabel,
+    GameState, Player, StartingPlayer,
+};
+use crate::{
+    menu::{self, MenuState},
+    states::{AppState, GameMode},
+    ui::UiAssets,
+};
+use bevy::{
+    input::{keyboard::KeyboardInput, mouse::MouseButtonInput},
+    prelude::*,
+    ui::FocusPolicy,
+};
+use rand::seq::IteratorRandom;
+
+/// How long the start screen waits for input before the demo starts behind it.
+const ATTRACT_DELAY: f32 = 3.;
+/// How long a finished demo stays on the board before the next one.
+const ATTRACT_RESTART_DELAY: f32 = 4.;
+const ATTRACT_ALPHA: f32 = 0.6;
+
+pub struct AttractPlugin;
+
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AttractTimer>()
+            .add_systems(OnEnter(AppState::Menu), (reset_timer, finish))
+            .add_systems(
+                Update,
+                start
+                    .run_if(not(resource_exists::<Attract>))
+                    .run_if(in_state(MenuState::Start))
+                    .run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(
+                OnEnter(AppState::Game),
+                setup.run_if(resource_exists::<Attract>),
+            )
+            .add_systems(
+                OnEnter(GameState::Over),
+                reset_timer.run_if(resource_exists::<Attract>),
+            )
+            .add_systems(
+                Update,
+                (
+                    menu::blink,
+                    name_labels,
+                    restart.run_if(in_state(GameState::Over)),
+                    listen,
+                )
+                    .run_if(resource_exists::<Attract>)
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(OnExit(AppState::Game), helpers::despawn::<AttractScreen>);
+    }
+}
+
+/// Present while a demo game plays behind the start screen. Holds what the demo changed, so it
+/// can be put back once someone wants to play.
+#[derive(Resource)]
+pub struct Attract {
+    time_control: TimeControl,
+}
+
+#[derive(Resource)]
+struct AttractTimer(Timer);
+
+impl Default for AttractTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(ATTRACT_DELAY, TimerMode::Once))
+    }
+}
+
+#[derive(Component)]
+struct AttractScreen;
+
+fn reset_timer(mut timer: ResMut<AttractTimer>, attract: Option<Res<Attract>>) {
+    let delay = if attract.is_some() {
+        ATTRACT_RESTART_DELAY
+    } else {
+        ATTRACT_DELAY
+    };
+
+    timer.0 = Timer::from_seconds(delay, TimerMode::Once);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start(
+    mut commands: Commands,
+    mut timer: ResMut<AttractTimer>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut next_game_mode: ResMut<NextState<GameMode>>,
+    mut time_control: ResMut<TimeControl>,
+    mut ai_players: ResMut<AiPlayers>,
+    mut agent_choice: ResMut<AgentChoice>,
+    mut ai_state: ResMut<NextState<AiState>>,
+    mut starting_player: ResMut<StartingPlayer>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let rng = rng.stream(Stream::Attract);
+
+    commands.insert_resource(Attract {
+        time_control: *time_control,
+    });
+
+    // a different matchup every time, the profile screen sets everything up again afterwards. The
+    // CPUs search in the background, so even the deepest ones leave the menu responsive
+    *time_control = TimeControl::Untimed;
+    *agent_choice = AgentChoice::default();
+    ai_players.0 = [(); 2].map(|_| {
+        Difficulty::iter()
+            .filter(|difficulty| *difficulty != Difficulty::Easy)
+            .choose(rng)
+    });
+    ai_state.set(AiState::Idle);
+
+    if let Some(game_mode) = GameMode::iter().choose(rng) {
+        next_game_mode.set(game_mode);
+    }
+
+    starting_player.0 = Player::iter().choose(rng).unwrap_or_default();
+    app_state.set(AppState::Game);
+}
+
+fn setup(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    let title = menu::spawn_title_screen(&mut commands, &ui_assets);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, ATTRACT_ALPHA).into(),
+                // nothing on the board underneath should react to the pointer
+                focus_policy: FocusPolicy::Block,
+                // above the pause menu and game over screen
+                z_index: ZIndex::Global(2),
+                ..default()
+            },
+            AttractScreen,
+        ))
+        .add_child(title);
+}
+
+fn name_labels(mut label_query: Query<&mut TurnIndicatorLabel, Added<TurnIndicatorLabel>>) {
+    for mut label in &mut label_query {
+        label.name = AI_NAME.to_string();
+    }
+}
+
+fn restart(
+    mut timer: ResMut<AttractTimer>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    app_state.set(AppState::Restart);
+    game_state.set(GameState::None);
+}
+
+fn listen(
+    keyboard_evr: EventReader<KeyboardInput>,
+    mut mouse_evr: EventReader<MouseButtonInput>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let clicked = mouse_evr.read().any(|event| event.state.is_pressed());
+
+    if keyboard_evr.is_empty() && !clicked {
+        return;
+    }
+
+    // leaving the game tears the demo down like any other, `finish` then carries on into the menu
+    app_state.set(AppState::Menu);
+    game_state.set(GameState::None);
+}
+
+/// Puts back what the demo changed and skips the start screen it stood in for.
+fn finish(
+    mut commands: Commands,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut time_control: ResMut<TimeControl>,
+    attract: Option<Res<Attract>>,
+) {
+    let Some(attract) = attract else {
+        return;
+    };
+
+    *time_control = attract.time_control;
+    commands.remove_resource::<Attract>();
+    menu_state.set(MenuState::Mode);
+}
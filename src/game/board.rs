@@ -1,11 +1,15 @@
 use super::{
-    ai::AiPlayer,
+    ai::AiPlayers,
     animation::AnimationState,
+    correspondence::Correspondence,
     helpers,
     marble::{MarbleOutlineEvent, MarbleStack},
+    network::Session,
+    pause::PauseState,
+    resign::pending_offer,
 };
 use crate::{
-    game::{Board, CurrentPlayer, Slot},
+    game::{Board, CurrentPlayer, GameState, Slot},
     states::AppState,
     ui::ReloadUiEvent,
 };
@@ -35,7 +39,11 @@ impl Plugin for BoardPlugin {
                 Update,
                 (
                     (clear_ui, draw_board).run_if(on_event::<ReloadUiEvent>()),
-                    handle_action.run_if(in_state(AnimationState::Idle)),
+                    handle_action
+                        .run_if(in_state(AnimationState::Idle))
+                        .run_if(in_state(PauseState::Running))
+                        .run_if(not(pending_offer))
+                        .run_if(not(in_state(GameState::Over))),
                     handle_hover,
                 )
                     .run_if(in_state(AppState::Game)),
@@ -48,7 +56,7 @@ impl Plugin for BoardPlugin {
 pub struct SlotPressEvent(pub Entity);
 
 #[derive(Event)]
-pub struct SlotHoverEvent(Entity, bool);
+pub struct SlotHoverEvent(pub Entity, pub bool);
 
 #[derive(Component)]
 pub struct SlotButton;
@@ -143,6 +151,7 @@ pub fn handle_hover(
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn handle_action(
     mut interaction_query: Query<
         (&Interaction, &SlotUi),
@@ -152,12 +161,26 @@ pub fn handle_action(
     mut slot_hover_events: EventWriter<SlotHoverEvent>,
     slot_query: Query<&Slot>,
     current_player: Res<CurrentPlayer>,
-    ai_player: Res<AiPlayer>,
+    ai_players: Res<AiPlayers>,
+    mut session: Option<ResMut<Session>>,
+    correspondence: Option<Res<Correspondence>>,
 ) {
+    // over the network only our own seat is playable, and only once the host answered our last move
+    if session.as_ref().is_some_and(|session| {
+        session.seat != Some(current_player.0) || session.awaiting_reply || session.waiting
+    }) {
+        return;
+    }
+
+    // a correspondence game only takes our own moves, once the opponent's have played out
+    if correspondence.is_some_and(|correspondence| !correspondence.is_our_turn(current_player.0)) {
+        return;
+    }
+
     for (interaction, slot_ui) in &mut interaction_query {
         let slot = slot_query.get(slot_ui.0).unwrap();
 
-        if ai_player.0 == Some(current_player.0)
+        if ai_players.controls(current_player.0)
             || Board::owner(slot.index) != current_player.0
             || slot.count == 0
         {
@@ -165,9 +188,13 @@ pub fn handle_action(
         }
 
         match *interaction {
-            Interaction::Pressed => {
-                slot_press_events.send(SlotPressEvent(slot_ui.0));
-            }
+            Interaction::Pressed => match &mut session {
+                // the host owns the board, so clients ask it to play the move for them
+                Some(session) if !session.is_host() => session.request_move(slot.index),
+                _ => {
+                    slot_press_events.send(SlotPressEvent(slot_ui.0));
+                }
+            },
             Interaction::Hovered => {
                 slot_hover_events.send(SlotHoverEvent(slot_ui.0, true));
             }
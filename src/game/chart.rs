@@ -0,0 +1,251 @@
+use super::{Board, GameRecord, MoveRecord, Player, Slot, TurnEndEvent};
+use crate::{states::AppState, ui::UiAssets};
+use bevy::prelude::*;
+
+const CHART_WIDTH: f32 = 400.;
+const CHART_HEIGHT: f32 = 200.;
+const LINE_THICKNESS: f32 = 3.;
+const MARKER_SIZE: f32 = 10.;
+
+// (132, 213, 226)
+pub const PLAYER_1_COLOR: Color = Color::rgb(0.5176471, 0.8352941, 0.8862745);
+// (223, 106, 180)
+pub const PLAYER_2_COLOR: Color = Color::rgb(0.8745098, 0.41568628, 0.7058824);
+const CAPTURE_COLOR: Color = Color::WHITE;
+const EXTRA_TURN_COLOR: Color = Color::YELLOW;
+const AXIS_COLOR: Color = Color::rgba(1., 1., 1., 0.3);
+
+pub struct ChartPlugin;
+
+impl Plugin for ChartPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScoreHistory>()
+            .add_systems(OnEnter(AppState::Game), reset)
+            .add_systems(
+                Update,
+                record_snapshot
+                    .run_if(on_event::<TurnEndEvent>())
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+/// Both stores as they stood once a turn finished animating.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScoreSnapshot {
+    pub stores: [u32; 2],
+    pub player: Player,
+    pub captured: bool,
+    pub extra_turn: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct ScoreHistory(pub Vec<ScoreSnapshot>);
+
+impl ScoreHistory {
+    /// Rebuilds the history of a game that was played elsewhere, from each move and the stores after it.
+    pub fn from_records(records: &[(MoveRecord, [u32; 2])]) -> Self {
+        let snapshots = records.iter().map(|(record, stores)| ScoreSnapshot {
+            stores: *stores,
+            player: record.player,
+            captured: record.captured > 0,
+            extra_turn: record.extra_turn,
+        });
+
+        Self(
+            std::iter::once(ScoreSnapshot::default())
+                .chain(snapshots)
+                .collect(),
+        )
+    }
+}
+
+fn reset(mut history: ResMut<ScoreHistory>) {
+    history.0 = vec![ScoreSnapshot::default()];
+}
+
+fn record_snapshot(
+    mut turn_end_events: EventReader<TurnEndEvent>,
+    mut history: ResMut<ScoreHistory>,
+    record: Res<GameRecord>,
+    board: Res<Board>,
+    slot_query: Query<&Slot>,
+) {
+    for _ in turn_end_events.read() {
+        let Some(last) = record.moves.last() else {
+            continue;
+        };
+
+        let mut stores = [0; 2];
+
+        for player in Player::iter() {
+            let store = board.slots[Board::get_store(player)];
+            stores[player as usize] = slot_query.get(store).map_or(0, |slot| slot.count);
+        }
+
+        history.0.push(ScoreSnapshot {
+            stores,
+            player: last.player,
+            captured: last.captured > 0,
+            extra_turn: last.extra_turn,
+        });
+    }
+}
+
+/// Draws each player's store over time, ending at `final_stores` so seeds swept at the end show.
+pub fn spawn_chart(
+    parent: &mut ChildBuilder,
+    ui_assets: &UiAssets,
+    history: &ScoreHistory,
+    final_stores: [u32; 2],
+) {
+    let mut snapshots = history.0.clone();
+
+    if snapshots.last().map(|snapshot| snapshot.stores) != Some(final_stores) {
+        snapshots.push(ScoreSnapshot {
+            stores: final_stores,
+            ..default()
+        });
+    }
+
+    let max_store = snapshots
+        .iter()
+        .flat_map(|snapshot| snapshot.stores)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let steps = (snapshots.len() - 1).max(1);
+
+    let point = |turn: usize, store: u32| {
+        Vec2::new(
+            CHART_WIDTH * turn as f32 / steps as f32,
+            CHART_HEIGHT * (1. - store as f32 / max_store as f32),
+        )
+    };
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(10.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(CHART_WIDTH),
+                        height: Val::Px(CHART_HEIGHT),
+                        border: UiRect {
+                            left: Val::Px(LINE_THICKNESS),
+                            bottom: Val::Px(LINE_THICKNESS),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    border_color: AXIS_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (player, color) in Player::iter().zip([PLAYER_1_COLOR, PLAYER_2_COLOR]) {
+                        let index = player as usize;
+
+                        for (turn, pair) in snapshots.windows(2).enumerate() {
+                            let start = point(turn, pair[0].stores[index]);
+                            let end = point(turn + 1, pair[1].stores[index]);
+
+                            spawn_segment(parent, start, end, color);
+                        }
+
+                        for (turn, snapshot) in snapshots.iter().enumerate() {
+                            if snapshot.player != player {
+                                continue;
+                            }
+
+                            let position = point(turn, snapshot.stores[index]);
+
+                            if snapshot.captured {
+                                spawn_marker(parent, position, CAPTURE_COLOR);
+                            } else if snapshot.extra_turn {
+                                spawn_marker(parent, position, EXTRA_TURN_COLOR);
+                            }
+                        }
+                    }
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(10.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (color, value) in [(CAPTURE_COLOR, "CAPTURE"), (EXTRA_TURN_COLOR, "EXTRA")]
+                    {
+                        parent.spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Px(MARKER_SIZE),
+                                height: Val::Px(MARKER_SIZE),
+                                ..default()
+                            },
+                            background_color: color.into(),
+                            ..default()
+                        });
+                        parent.spawn(TextBundle::from_section(
+                            value,
+                            TextStyle {
+                                font: ui_assets.font.clone(),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                        ));
+                    }
+                });
+        });
+}
+
+fn spawn_segment(parent: &mut ChildBuilder, start: Vec2, end: Vec2, color: Color) {
+    let delta = end - start;
+    let length = delta.length();
+    let center = (start + end) / 2.;
+
+    parent.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(center.x - length / 2.),
+            top: Val::Px(center.y - LINE_THICKNESS / 2.),
+            width: Val::Px(length),
+            height: Val::Px(LINE_THICKNESS),
+            ..default()
+        },
+        // ui layout only ever touches the translation, so the rotation sticks
+        transform: Transform::from_rotation(Quat::from_rotation_z(delta.y.atan2(delta.x))),
+        background_color: color.into(),
+        ..default()
+    });
+}
+
+fn spawn_marker(parent: &mut ChildBuilder, position: Vec2, color: Color) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(position.x - MARKER_SIZE / 2.),
+            top: Val::Px(position.y - MARKER_SIZE / 2.),
+            width: Val::Px(MARKER_SIZE),
+            height: Val::Px(MARKER_SIZE),
+            ..default()
+        },
+        background_color: color.into(),
+        z_index: ZIndex::Local(1),
+        ..default()
+    });
+}
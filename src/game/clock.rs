@@ -0,0 +1,196 @@
+use super::{
+    ai::AiPlayers,
+    animation::AnimationState,
+    board::SlotPressEvent,
+    network::{waiting, Session},
+    pause::halted,
+    resign::pending_offer,
+    rng::{GameRng, Stream},
+    Board, CurrentPlayer, EndReason, GameRecord, GameState, Slot, TurnEndEvent, Winner,
+};
+use crate::states::AppState;
+use bevy::prelude::*;
+use rand::seq::IteratorRandom;
+use std::{fmt, time::Duration};
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeControl>()
+            .init_resource::<Clock>()
+            .add_systems(OnEnter(AppState::Game), reset)
+            .add_systems(
+                Update,
+                (
+                    handle_turn_end,
+                    tick.run_if(in_state(GameState::Idle))
+                        .run_if(in_state(AnimationState::Idle))
+                        .run_if(not(halted))
+                        .run_if(not(pending_offer))
+                        .run_if(not(waiting)),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    #[default]
+    Untimed,
+    /// A single budget for the whole game.
+    SuddenDeath { total: Duration },
+    /// A budget for the whole game that grows by `increment` after every move.
+    Fischer {
+        total: Duration,
+        increment: Duration,
+    },
+    /// A fresh budget for every move. Running out plays a random move instead of losing.
+    PerMove { limit: Duration },
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Untimed => write!(f, "NONE"),
+            Self::SuddenDeath { total } => write!(f, "{} MIN", total.as_secs() / 60),
+            Self::Fischer { total, increment } => {
+                write!(f, "{}+{}", total.as_secs() / 60, increment.as_secs())
+            }
+            Self::PerMove { limit } => write!(f, "{}S MOVE", limit.as_secs()),
+        }
+    }
+}
+
+impl TimeControl {
+    pub const PRESETS: [TimeControl; 4] = [
+        TimeControl::Untimed,
+        TimeControl::SuddenDeath {
+            total: Duration::from_secs(5 * 60),
+        },
+        TimeControl::Fischer {
+            total: Duration::from_secs(3 * 60),
+            increment: Duration::from_secs(2),
+        },
+        TimeControl::PerMove {
+            limit: Duration::from_secs(10),
+        },
+    ];
+
+    /// The preset after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::PRESETS
+            .iter()
+            .position(|preset| *preset == self)
+            .map_or(0, |index| (index + 1) % Self::PRESETS.len());
+
+        Self::PRESETS[index]
+    }
+
+    fn budget(self) -> Duration {
+        match self {
+            Self::Untimed => Duration::ZERO,
+            Self::SuddenDeath { total } | Self::Fischer { total, .. } => total,
+            Self::PerMove { limit } => limit,
+        }
+    }
+}
+
+/// Time left for each player, indexed by `Player as usize`.
+#[derive(Resource, Default)]
+pub struct Clock {
+    pub remaining: [Duration; 2],
+}
+
+impl Clock {
+    /// Formats a player's remaining time as `m:ss`, rounding up so `0:00` means out of time.
+    pub fn format(&self, index: usize) -> String {
+        let seconds = self.remaining[index].as_secs_f32().ceil() as u64;
+
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+fn reset(mut clock: ResMut<Clock>, time_control: Res<TimeControl>) {
+    clock.remaining = [time_control.budget(); 2];
+}
+
+fn handle_turn_end(
+    mut turn_end_events: EventReader<TurnEndEvent>,
+    mut clock: ResMut<Clock>,
+    time_control: Res<TimeControl>,
+    record: Res<GameRecord>,
+) {
+    for _ in turn_end_events.read() {
+        let Some(last) = record.moves.last() else {
+            continue;
+        };
+
+        match *time_control {
+            TimeControl::Fischer { increment, .. } => {
+                clock.remaining[last.player as usize] += increment;
+            }
+            TimeControl::PerMove { limit } => {
+                clock.remaining = [limit; 2];
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn tick(
+    mut clock: ResMut<Clock>,
+    mut winner: ResMut<Winner>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut slot_press_events: EventWriter<SlotPressEvent>,
+    time_control: Res<TimeControl>,
+    current_player: Res<CurrentPlayer>,
+    ai_players: Res<AiPlayers>,
+    board: Res<Board>,
+    slot_query: Query<&Slot>,
+    time: Res<Time>,
+    session: Option<Res<Session>>,
+    mut rng: ResMut<GameRng>,
+) {
+    if *time_control == TimeControl::Untimed {
+        return;
+    }
+
+    let index = current_player.0 as usize;
+    let remaining = clock.remaining[index].saturating_sub(time.delta());
+
+    clock.remaining[index] = remaining;
+
+    // a client's clock is only for show, the host decides when time runs out
+    if !remaining.is_zero() || session.is_some_and(|session| !session.is_host()) {
+        return;
+    }
+
+    match *time_control {
+        TimeControl::PerMove { limit } => {
+            // agents are cut off where they're polled, so their move isn't pressed twice
+            if ai_players.controls(current_player.0) {
+                return;
+            }
+
+            let choice = Board::get_slots(current_player.0)
+                .map(|index| board.slots[index])
+                .filter(|slot| slot_query.get(*slot).is_ok_and(|slot| slot.count > 0))
+                .choose(rng.stream(Stream::Clock));
+
+            if let Some(slot) = choice {
+                slot_press_events.send(SlotPressEvent(slot));
+            }
+
+            clock.remaining[index] = limit;
+        }
+        _ => {
+            winner.player = Some(current_player.0.flip());
+            winner.reason = EndReason::Timeout;
+            game_state.set(GameState::Over);
+        }
+    }
+}
@@ -0,0 +1,621 @@
+use crate::{
+    game::{rules::Position, MoveRecord, Player},
+    states::GameMode,
+};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+const HEADER: &str = "MANCALA CORRESPONDENCE 3";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileError {
+    Malformed,
+    /// The file names a mode this build doesn't know.
+    UnknownMode,
+    IllegalMove(usize),
+    BadSignature(usize),
+    /// The second seat is taken without its holder's signature on it.
+    BadClaim,
+    /// Neither seat belongs to us and both are taken.
+    NotOurs,
+    /// Fewer moves than the copy we last saw, taking moves back.
+    Rewound,
+    /// Moves that aren't the ones in the copy we last saw.
+    Diverged,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "NOT A GAME FILE"),
+            Self::UnknownMode => write!(f, "UNKNOWN MODE"),
+            Self::IllegalMove(number) => write!(f, "ILLEGAL MOVE {}", number + 1),
+            Self::BadSignature(number) => write!(f, "BAD SIGNATURE ON MOVE {}", number + 1),
+            Self::BadClaim => write!(f, "BAD SIGNATURE ON SEAT 2"),
+            Self::NotOurs => write!(f, "NOT YOUR GAME"),
+            Self::Rewound => write!(f, "OLDER THAN THE LAST COPY"),
+            Self::Diverged => write!(f, "DOESN'T MATCH THE LAST COPY"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Seat {
+    /// Unset until someone takes this seat.
+    pub key: Option<VerifyingKey>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignedMove {
+    pub index: usize,
+    /// Made by the mover over the game id, mode, the seats taken so far and every move up to and
+    /// including this one, so it vouches for the whole game up to here.
+    pub signature: Signature,
+}
+
+/// The second seat being taken, signed by whoever took it.
+#[derive(Debug, Clone)]
+pub struct Claim {
+    /// How many moves had been made when the seat was taken.
+    pub at: usize,
+    /// Made over the game id, mode, both seats and `at`.
+    pub signature: Signature,
+}
+
+/// A correspondence game as exchanged between players, one move signed at a time.
+#[derive(Debug, Clone)]
+pub struct GameFile {
+    pub id: u64,
+    pub mode: GameMode,
+    pub seats: [Seat; 2],
+    /// Unset until someone takes the second seat.
+    pub claim: Option<Claim>,
+    pub moves: Vec<SignedMove>,
+}
+
+/// How far a game had got the last time we saw it, so an older or forked copy can't be passed off
+/// as the latest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    pub moves: usize,
+    /// The last move's signature, which covers every move before it.
+    pub last: Option<String>,
+}
+
+/// The position a file's moves lead to, along with what each move did.
+pub struct Replay {
+    pub position: Position,
+    pub records: Vec<(MoveRecord, [u32; 2])>,
+}
+
+impl GameFile {
+    pub fn new(mode: GameMode, key: &SigningKey, name: &str) -> Self {
+        Self {
+            id: rand::random(),
+            mode,
+            seats: [
+                Seat {
+                    key: Some(key.verifying_key()),
+                    name: name.to_string(),
+                },
+                Seat::default(),
+            ],
+            claim: None,
+            moves: vec![],
+        }
+    }
+
+    /// Whether the second seat was taken before move `number` was made.
+    fn joined_by(&self, number: usize) -> bool {
+        self.claim.as_ref().is_some_and(|claim| claim.at <= number)
+    }
+
+    /// The game and the keys and names of the seats, leaving out the second unless it's been taken.
+    fn seats_text(&self, joined: bool) -> String {
+        let mut text = format!("{:016x} {}", self.id, self.mode);
+
+        for (seat, taken) in self.seats.iter().zip([true, joined]) {
+            match seat.key {
+                // quoted, since names can hold spaces
+                Some(key) if taken => {
+                    text.push_str(&format!(" {} {:?}", to_hex(key.as_bytes()), seat.name))
+                }
+                _ => text.push_str(" -"),
+            }
+        }
+
+        text
+    }
+
+    /// What the second seat's claim covers.
+    fn claim_text(&self, at: usize) -> String {
+        format!("{} CLAIM {at}", self.seats_text(true))
+    }
+
+    /// What move `number`'s signature covers, given every move up to and including it.
+    fn signed_text(&self, number: usize, indices: impl Iterator<Item = usize>) -> String {
+        let mut text = self.seats_text(self.joined_by(number));
+
+        for index in indices {
+            text.push_str(&format!(" {index}"));
+        }
+
+        text
+    }
+
+    /// Which seat is ours, claiming the empty second seat if we're the one joining.
+    pub fn seat_for(&mut self, key: &SigningKey, name: &str) -> Result<Player, FileError> {
+        let verifying_key = key.verifying_key();
+
+        for player in Player::iter() {
+            if self.seats[player as usize].key == Some(verifying_key) {
+                return Ok(player);
+            }
+        }
+
+        let seat = &mut self.seats[Player::Two as usize];
+
+        if seat.key.is_some() {
+            return Err(FileError::NotOurs);
+        }
+
+        seat.key = Some(verifying_key);
+        seat.name = name.to_string();
+
+        let at = self.moves.len();
+
+        self.claim = Some(Claim {
+            at,
+            signature: key.sign(self.claim_text(at).as_bytes()),
+        });
+
+        Ok(Player::Two)
+    }
+
+    /// Whether the second seat is either empty or signed for by whoever holds it.
+    fn verify_claim(&self) -> Result<(), FileError> {
+        match (self.seats[Player::Two as usize].key, &self.claim) {
+            (None, None) => Ok(()),
+            (Some(key), Some(claim)) if claim.at <= self.moves.len() => key
+                .verify(self.claim_text(claim.at).as_bytes(), &claim.signature)
+                .map_err(|_| FileError::BadClaim),
+            _ => Err(FileError::BadClaim),
+        }
+    }
+
+    /// Replays every move from the start, checking the second seat's claim, and that each move is
+    /// legal and signed by its mover.
+    pub fn replay(&self) -> Result<Replay, FileError> {
+        self.verify_claim()?;
+
+        let mut position = Position::new(Player::One);
+        let mut records = vec![];
+
+        for (number, signed) in self.moves.iter().enumerate() {
+            let player = position.current;
+
+            if position.is_over() || !position.is_legal(signed.index) {
+                return Err(FileError::IllegalMove(number));
+            }
+
+            let text = self.signed_text(
+                number,
+                self.moves[..=number].iter().map(|signed| signed.index),
+            );
+            // nobody can move for the second seat before taking it
+            let seated = player == Player::One || self.joined_by(number);
+            let verified = seated
+                && self.seats[player as usize]
+                    .key
+                    .is_some_and(|key| key.verify(text.as_bytes(), &signed.signature).is_ok());
+
+            if !verified {
+                return Err(FileError::BadSignature(number));
+            }
+
+            let outcome = position.play(signed.index, self.mode);
+
+            records.push((
+                MoveRecord {
+                    player,
+                    index: signed.index,
+                    captured: outcome.captured,
+                    extra_turn: outcome.extra_turn,
+                },
+                [position.store(Player::One), position.store(Player::Two)],
+            ));
+        }
+
+        Ok(Replay { position, records })
+    }
+
+    /// Appends our move, signed with `key`.
+    pub fn push(&mut self, index: usize, key: &SigningKey) {
+        let text = self.signed_text(
+            self.moves.len(),
+            self.moves
+                .iter()
+                .map(|signed| signed.index)
+                .chain(std::iter::once(index)),
+        );
+
+        self.moves.push(SignedMove {
+            index,
+            signature: key.sign(text.as_bytes()),
+        });
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            moves: self.moves.len(),
+            last: self
+                .moves
+                .last()
+                .map(|signed| to_hex(&signed.signature.to_bytes())),
+        }
+    }
+
+    /// Whether this is the game as `seen` last, or carries on from it. Only meaningful once the
+    /// file's been replayed, since the signatures are taken on trust here.
+    pub fn follows(&self, seen: &Progress) -> Result<(), FileError> {
+        if self.moves.len() < seen.moves {
+            return Err(FileError::Rewound);
+        }
+
+        let last = seen
+            .moves
+            .checked_sub(1)
+            .map(|number| to_hex(&self.moves[number].signature.to_bytes()));
+
+        if last != seen.last {
+            return Err(FileError::Diverged);
+        }
+
+        Ok(())
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{:016x}-{:03}.mancala", self.id, self.moves.len())
+    }
+}
+
+impl fmt::Display for GameFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        writeln!(f, "GAME {:016x}", self.id)?;
+        writeln!(f, "MODE {}", self.mode)?;
+
+        for (number, seat) in self.seats.iter().enumerate() {
+            let key = seat
+                .key
+                .map_or("-".to_string(), |key| to_hex(key.as_bytes()));
+
+            writeln!(f, "SEAT {} {key} {}", number + 1, seat.name)?;
+        }
+
+        match &self.claim {
+            Some(claim) => writeln!(
+                f,
+                "CLAIM {} {}",
+                claim.at,
+                to_hex(&claim.signature.to_bytes())
+            )?,
+            None => writeln!(f, "CLAIM -")?,
+        }
+
+        for signed in &self.moves {
+            writeln!(
+                f,
+                "MOVE {} {}",
+                signed.index,
+                to_hex(&signed.signature.to_bytes())
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for GameFile {
+    type Err = FileError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines().map(|line| line.trim_end_matches('\r'));
+
+        if lines.next() != Some(HEADER) {
+            return Err(FileError::Malformed);
+        }
+
+        let id = lines
+            .next()
+            .and_then(|line| line.strip_prefix("GAME "))
+            .and_then(|id| u64::from_str_radix(id, 16).ok())
+            .ok_or(FileError::Malformed)?;
+
+        let mode = lines
+            .next()
+            .and_then(|line| line.strip_prefix("MODE "))
+            .ok_or(FileError::Malformed)?
+            .parse()
+            .map_err(|_| FileError::UnknownMode)?;
+
+        let mut file = Self {
+            id,
+            mode,
+            seats: Default::default(),
+            claim: None,
+            moves: vec![],
+        };
+
+        for seat in &mut file.seats {
+            let line = lines.next().ok_or(FileError::Malformed)?;
+            let mut parts = line.splitn(4, ' ');
+
+            if parts.next() != Some("SEAT") || parts.next().is_none() {
+                return Err(FileError::Malformed);
+            }
+
+            seat.key = match parts.next().ok_or(FileError::Malformed)? {
+                "-" => None,
+                key => {
+                    let bytes = from_hex::<32>(key).ok_or(FileError::Malformed)?;
+                    Some(VerifyingKey::from_bytes(&bytes).map_err(|_| FileError::Malformed)?)
+                }
+            };
+            seat.name = parts.next().unwrap_or_default().to_string();
+        }
+
+        let claim = lines
+            .next()
+            .and_then(|line| line.strip_prefix("CLAIM "))
+            .ok_or(FileError::Malformed)?;
+
+        if claim != "-" {
+            let (at, signature) = claim.split_once(' ').ok_or(FileError::Malformed)?;
+
+            file.claim = Some(Claim {
+                at: at.parse().map_err(|_| FileError::Malformed)?,
+                signature: Signature::from_bytes(
+                    &from_hex::<64>(signature).ok_or(FileError::Malformed)?,
+                ),
+            });
+        }
+
+        for line in lines.filter(|line| !line.is_empty()) {
+            let mut parts = line.split(' ');
+
+            if parts.next() != Some("MOVE") {
+                return Err(FileError::Malformed);
+            }
+
+            let index = parts
+                .next()
+                .and_then(|index| index.parse().ok())
+                .ok_or(FileError::Malformed)?;
+            let signature = parts
+                .next()
+                .and_then(from_hex::<64>)
+                .ok_or(FileError::Malformed)?;
+
+            file.moves.push(SignedMove {
+                index,
+                signature: Signature::from_bytes(&signature),
+            });
+        }
+
+        Ok(file)
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn from_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    if text.len() != N * 2 || !text.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0; N];
+
+    for (byte, pair) in bytes.iter_mut().zip(text.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    /// A game where each move is the legal move picked by the remainder of its choice, with the
+    /// second seat taken right before its first move.
+    fn game(choices: &[usize]) -> GameFile {
+        let keys = [key(1), key(2)];
+        let mut file = GameFile::new(GameMode::Capture, &keys[0], "PLAYER ONE");
+
+        for choice in choices {
+            let position = file.replay().unwrap().position;
+            let player = position.current;
+
+            if player == Player::Two && file.claim.is_none() {
+                assert_eq!(file.seat_for(&keys[1], "PLAYER TWO"), Ok(Player::Two));
+            }
+
+            let legal: Vec<usize> = position.legal_moves().collect();
+
+            file.push(legal[choice % legal.len()], &keys[player as usize]);
+        }
+
+        file
+    }
+
+    /// Parses and replays `text`, as opening a file does.
+    fn open(text: &str) -> Result<Replay, FileError> {
+        text.parse::<GameFile>()?.replay()
+    }
+
+    #[test]
+    fn signed_games_survive_the_round_trip() {
+        let file = game(&[0; 12]);
+        let text = file.to_string();
+        let parsed: GameFile = text.parse().unwrap();
+
+        assert_eq!(parsed.to_string(), text);
+        assert_eq!(parsed.seats[1].name, "PLAYER TWO");
+        assert_eq!(parsed.replay().unwrap().records.len(), 12);
+        // both seats find themselves again
+        assert_eq!(parsed.clone().seat_for(&key(1), "X"), Ok(Player::One));
+        assert_eq!(parsed.clone().seat_for(&key(2), "X"), Ok(Player::Two));
+        assert_eq!(
+            parsed.clone().seat_for(&key(3), "X"),
+            Err(FileError::NotOurs)
+        );
+    }
+
+    #[test]
+    fn changed_moves_are_refused() {
+        let file = game(&[0; 12]);
+        let mut position = Position::new(Player::One);
+
+        for number in 0..file.moves.len() {
+            for index in position.legal_moves() {
+                if index == file.moves[number].index {
+                    continue;
+                }
+
+                let mut changed = file.clone();
+                changed.moves[number].index = index;
+
+                assert_eq!(
+                    open(&changed.to_string()).err(),
+                    Some(FileError::BadSignature(number))
+                );
+            }
+
+            position.play(file.moves[number].index, file.mode);
+        }
+    }
+
+    #[test]
+    fn moves_signed_by_anyone_else_are_refused() {
+        let file = game(&[0; 12]);
+
+        for number in 0..file.moves.len() {
+            let mut forged = file.clone();
+            let text = forged.signed_text(
+                number,
+                forged.moves[..=number].iter().map(|signed| signed.index),
+            );
+
+            forged.moves[number].signature = key(3).sign(text.as_bytes());
+
+            assert_eq!(
+                open(&forged.to_string()).err(),
+                Some(FileError::BadSignature(number))
+            );
+        }
+    }
+
+    #[test]
+    fn seats_claimed_by_anyone_else_are_refused() {
+        let file = game(&[0; 12]);
+        let at = file.claim.as_ref().unwrap().at;
+
+        // signed by someone other than the second seat
+        let mut forged = file.clone();
+        forged.claim.as_mut().unwrap().signature = key(3).sign(forged.claim_text(at).as_bytes());
+
+        assert_eq!(open(&forged.to_string()).err(), Some(FileError::BadClaim));
+
+        // the second seat swapped for someone else, keeping the claim
+        let mut swapped = file.clone();
+        swapped.seats[1].key = Some(key(3).verifying_key());
+
+        assert_eq!(open(&swapped.to_string()).err(), Some(FileError::BadClaim));
+
+        // claimed later than it was, so the moves before it would have been made from nowhere
+        let mut moved = file.clone();
+        moved.claim.as_mut().unwrap().at = at + 1;
+
+        assert_eq!(open(&moved.to_string()).err(), Some(FileError::BadClaim));
+
+        // taken without a claim at all
+        let mut unclaimed = file.clone();
+        unclaimed.claim = None;
+
+        assert_eq!(
+            open(&unclaimed.to_string()).err(),
+            Some(FileError::BadClaim)
+        );
+    }
+
+    #[test]
+    fn names_are_signed() {
+        // only the first seat's moves so far, so there's no claim to fail first
+        let mut renamed = game(&[0]);
+        renamed.seats[0].name = "SOMEONE ELSE".to_string();
+
+        assert_eq!(
+            open(&renamed.to_string()).err(),
+            Some(FileError::BadSignature(0))
+        );
+
+        // the claim covers both names
+        for seat in 0..2 {
+            let mut renamed = game(&[0; 12]);
+            renamed.seats[seat].name = "SOMEONE ELSE".to_string();
+
+            assert_eq!(open(&renamed.to_string()).err(), Some(FileError::BadClaim));
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_refused() {
+        let text = game(&[0; 12]).to_string();
+        let bytes = text.as_bytes();
+
+        for cut in 0..text.len() {
+            // cutting between lines leaves an earlier copy of the game, which `follows` is for
+            if cut > 0 && (bytes[cut - 1] == b'\n' || bytes[cut] == b'\n') {
+                continue;
+            }
+
+            assert!(open(&text[..cut]).is_err(), "{:?}", &text[..cut]);
+        }
+    }
+
+    #[test]
+    fn only_the_game_as_last_seen_or_later_is_followed() {
+        let file = game(&[0; 12]);
+        let seen = {
+            let mut earlier = file.clone();
+            earlier.moves.truncate(8);
+            earlier.progress()
+        };
+
+        assert_eq!(file.follows(&seen), Ok(()));
+        assert_eq!(file.follows(&file.progress()), Ok(()));
+
+        let mut rewound = file.clone();
+        rewound.moves.truncate(7);
+
+        assert_eq!(rewound.follows(&seen), Err(FileError::Rewound));
+
+        // the same game up to move 6, played differently from there
+        let mut choices = [0; 12];
+        choices[6] = 1;
+        let forked = game(&choices);
+
+        assert!(forked.replay().is_ok());
+        assert_ne!(forked.moves[6].index, file.moves[6].index);
+        assert_eq!(forked.follows(&seen), Err(FileError::Diverged));
+    }
+}
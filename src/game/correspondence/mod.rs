@@ -0,0 +1,489 @@
+use self::file::{from_hex, to_hex, GameFile, Progress, Replay};
+use super::{
+    ai::{AiPlayers, AiState},
+    animation::AnimationState,
+    board::SlotPressEvent,
+    chart::ScoreHistory,
+    clock::TimeControl,
+    helpers,
+    marble::{self, MarbleEvent, MarbleStack},
+    rules::Position,
+    turn_indicator::TurnIndicatorLabel,
+    Board, CurrentPlayer, GameRecord, GameState, Player, Slot, StartingPlayer,
+};
+use crate::{
+    menu::{MenuState, Selected},
+    profile::{ConfigDir, Profiles},
+    states::{AppState, GameMode},
+    ui::UiAssets,
+};
+use bevy::prelude::*;
+use bevy_persistent::prelude::*;
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub mod file;
+
+pub struct CorrespondencePlugin;
+
+impl Plugin for CorrespondencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CorrespondenceEvent>()
+            .init_resource::<MailStatus>()
+            .add_systems(Startup, (load_identity, load_seen))
+            .add_systems(Update, handle_events.run_if(in_state(AppState::Menu)))
+            .add_systems(
+                Update,
+                (
+                    restore,
+                    replay_next
+                        .run_if(in_state(GameState::Idle))
+                        .run_if(in_state(AnimationState::Idle)),
+                    sign_moves,
+                    update_banner,
+                )
+                    .chain()
+                    .run_if(resource_exists::<Correspondence>)
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(OnExit(AppState::Game), (end, helpers::despawn::<Banner>));
+    }
+}
+
+/// Sent by the menu to start a correspondence game or pick one up from a file.
+#[derive(Event)]
+pub enum CorrespondenceEvent {
+    New,
+    Open(PathBuf),
+}
+
+/// Why the last file couldn't be opened, shown on the menu.
+#[derive(Resource, Default)]
+pub struct MailStatus(pub String);
+
+/// The key every move we make is signed with, kept between runs in `identity.ron`.
+///
+/// That file is a secret: anyone holding it can sign moves as us, so it's only readable by its
+/// owner and should never be shared or sent along with a game file.
+#[derive(Resource, Serialize, Deserialize)]
+struct Identity {
+    secret: String,
+}
+
+impl Identity {
+    fn generate() -> Self {
+        Self {
+            secret: to_hex(&rand::random::<[u8; 32]>()),
+        }
+    }
+
+    fn key(&self) -> Option<SigningKey> {
+        from_hex::<32>(&self.secret).map(|bytes| SigningKey::from_bytes(&bytes))
+    }
+}
+
+/// How far each game we've played had got, by id, kept between runs in `seen.ron`, so nobody can
+/// take moves back by sending us an older copy of the file.
+#[derive(Resource, Serialize, Deserialize, Default)]
+struct Seen(HashMap<u64, Progress>);
+
+impl Seen {
+    fn record(seen: &mut Persistent<Seen>, file: &GameFile) {
+        let progress = file.progress();
+
+        if let Err(error) = seen.update(|seen| {
+            seen.0.insert(file.id, progress.clone());
+        }) {
+            warn!("failed to remember game {:016x}: {error}", file.id);
+        }
+    }
+}
+
+/// A game played by exchanging files. Only present while playing one.
+#[derive(Resource)]
+pub struct Correspondence {
+    pub file: GameFile,
+    /// The seat played on this machine.
+    pub seat: Player,
+    key: SigningKey,
+    /// Mirrors the board, so each move can be put down to whoever made it.
+    position: Position,
+    /// Set up without animating, before anything else happens.
+    restored: Option<Replay>,
+    /// The opponent's last turn, animated once the rest is restored.
+    replay: VecDeque<usize>,
+    saved: Option<Result<PathBuf, String>>,
+}
+
+impl Correspondence {
+    /// Whether we can move right now, and not just watch the opponent's turn play out.
+    pub fn is_our_turn(&self, current_player: Player) -> bool {
+        current_player == self.seat && self.restored.is_none() && self.replay.is_empty()
+    }
+
+    fn opponent(&self) -> String {
+        let name = &self.file.seats[self.seat.flip() as usize].name;
+
+        if name.is_empty() {
+            "OPPONENT".to_string()
+        } else {
+            name.clone()
+        }
+    }
+}
+
+#[derive(Component)]
+struct Banner;
+
+/// Where game files are written to and read from.
+pub fn inbox(config_dir: &ConfigDir) -> PathBuf {
+    config_dir.0.join("correspondence")
+}
+
+fn load_identity(mut commands: Commands, config_dir: Res<ConfigDir>) {
+    let path = config_dir.0.join("identity.ron");
+
+    if let Err(error) = make_private(&path) {
+        warn!("failed to restrict {}: {error}", path.display());
+    }
+
+    let mut identity = Persistent::<Identity>::builder()
+        .name("identity")
+        .format(StorageFormat::RonPrettyWithStructNames)
+        .path(path)
+        .default(Identity::generate())
+        // the file made private above starts out empty
+        .revertible(true)
+        .revert_to_default_on_deserialization_errors(true)
+        .build()
+        .expect("failed to initialize identity");
+
+    if identity.key().is_none() {
+        identity
+            .set(Identity::generate())
+            .expect("failed to replace identity");
+    }
+
+    commands.insert_resource(identity);
+}
+
+fn load_seen(mut commands: Commands, config_dir: Res<ConfigDir>) {
+    commands.insert_resource(
+        Persistent::<Seen>::builder()
+            .name("seen")
+            .format(StorageFormat::RonPrettyWithStructNames)
+            .path(config_dir.0.join("seen.ron"))
+            .default(Seen::default())
+            .build()
+            .expect("failed to initialize seen games"),
+    );
+}
+
+/// Creates `path` readable and writable by its owner alone, before the secret is written to it,
+/// and tightens it if an earlier run left it open to others.
+#[cfg(unix)]
+fn make_private(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        // an identity that's already there is kept
+        .truncate(false)
+        .mode(0o600)
+        .open(path)?;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn make_private(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn open(path: &Path, key: SigningKey, name: &str, seen: &Seen) -> Result<Correspondence, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("CAN'T READ: {}", error.kind()))?;
+    let mut file: GameFile = text
+        .parse()
+        .map_err(|error: file::FileError| error.to_string())?;
+    let seat = file
+        .seat_for(&key, name)
+        .map_err(|error| error.to_string())?;
+    let replay = file.replay().map_err(|error| error.to_string())?;
+
+    if let Some(progress) = seen.0.get(&file.id) {
+        file.follows(progress).map_err(|error| error.to_string())?;
+    }
+
+    // the opponent's moves since our last one are animated, everything before is restored
+    let unseen = replay
+        .records
+        .iter()
+        .rev()
+        .take_while(|(record, _)| record.player != seat)
+        .count();
+    let seen = file.moves.len() - unseen;
+
+    let mut prefix = file.clone();
+    prefix.moves.truncate(seen);
+
+    let restored = prefix.replay().map_err(|error| error.to_string())?;
+    let replay = file.moves[seen..]
+        .iter()
+        .map(|signed| signed.index)
+        .collect();
+
+    Ok(Correspondence {
+        file,
+        seat,
+        key,
+        position: restored.position.clone(),
+        restored: Some(restored),
+        replay,
+        saved: None,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_events(
+    mut commands: Commands,
+    mut events: EventReader<CorrespondenceEvent>,
+    mut status: ResMut<MailStatus>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut next_game_mode: ResMut<NextState<GameMode>>,
+    mut time_control: ResMut<TimeControl>,
+    mut ai_players: ResMut<AiPlayers>,
+    mut ai_state: ResMut<NextState<AiState>>,
+    mut starting_player: ResMut<StartingPlayer>,
+    game_mode: Res<State<GameMode>>,
+    identity: Res<Persistent<Identity>>,
+    mut seen: ResMut<Persistent<Seen>>,
+    selected: Res<Selected>,
+    profiles: Res<Persistent<Profiles>>,
+) {
+    let name = &profiles.0[selected.get(Player::One)].name;
+
+    for event in events.read() {
+        let Some(key) = identity.key() else {
+            status.0 = "NO IDENTITY".to_string();
+            continue;
+        };
+
+        let correspondence = match event {
+            CorrespondenceEvent::New => {
+                let file = GameFile::new(*game_mode.get(), &key, name);
+
+                Ok(Correspondence {
+                    file,
+                    seat: Player::One,
+                    key,
+                    position: Position::new(Player::One),
+                    restored: Some(Replay {
+                        position: Position::new(Player::One),
+                        records: vec![],
+                    }),
+                    replay: VecDeque::new(),
+                    saved: None,
+                })
+            }
+            CorrespondenceEvent::Open(path) => open(path, key, name, &seen),
+        };
+
+        let correspondence = match correspondence {
+            Ok(correspondence) => correspondence,
+            Err(error) => {
+                status.0 = error;
+                continue;
+            }
+        };
+
+        status.0.clear();
+        Seen::record(&mut seen, &correspondence.file);
+
+        next_game_mode.set(correspondence.file.mode);
+        commands.insert_resource(correspondence);
+
+        // correspondence games are untimed and between two humans
+        *time_control = TimeControl::Untimed;
+        *ai_players = AiPlayers::default();
+        ai_state.set(AiState::Inactive);
+
+        starting_player.0 = Player::One;
+        app_state.set(AppState::Game);
+        menu_state.set(MenuState::Start);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restore(
+    mut correspondence: ResMut<Correspondence>,
+    mut slot_query: Query<(Entity, &mut Slot)>,
+    mut label_query: Query<&mut TurnIndicatorLabel>,
+    mut marble_events: EventWriter<MarbleEvent>,
+    mut current_player: ResMut<CurrentPlayer>,
+    mut record: ResMut<GameRecord>,
+    mut history: ResMut<ScoreHistory>,
+    stack_query: Query<&MarbleStack>,
+) {
+    let Some(restored) = &correspondence.restored else {
+        return;
+    };
+
+    if !marble::restack(
+        &restored.position.counts,
+        &mut slot_query,
+        &stack_query,
+        &mut marble_events,
+    ) {
+        return;
+    }
+
+    let Some(restored) = correspondence.restored.take() else {
+        return;
+    };
+
+    current_player.0 = restored.position.current;
+    *record = GameRecord::from_records(&restored.records);
+    *history = ScoreHistory::from_records(&restored.records);
+
+    for mut label in &mut label_query {
+        let name = &correspondence.file.seats[label.player as usize].name;
+
+        label.name = if name.is_empty() {
+            "?".to_string()
+        } else {
+            name.clone()
+        };
+    }
+}
+
+/// Plays the opponent's last turn one move at a time, so each one animates.
+fn replay_next(
+    mut correspondence: ResMut<Correspondence>,
+    mut slot_press_events: EventWriter<SlotPressEvent>,
+    board: Res<Board>,
+) {
+    if correspondence.restored.is_some() {
+        return;
+    }
+
+    if let Some(index) = correspondence.replay.pop_front() {
+        slot_press_events.send(SlotPressEvent(board.slots[index]));
+    }
+}
+
+/// Signs our moves as they're played, writing the file once the turn passes or the game ends.
+fn sign_moves(
+    mut correspondence: ResMut<Correspondence>,
+    mut slot_press_events: EventReader<SlotPressEvent>,
+    mut seen: ResMut<Persistent<Seen>>,
+    slot_query: Query<&Slot>,
+    config_dir: Res<ConfigDir>,
+) {
+    let correspondence = correspondence.as_mut();
+    let mut moved = false;
+
+    for SlotPressEvent(slot) in slot_press_events.read() {
+        let Ok(slot) = slot_query.get(*slot) else {
+            continue;
+        };
+
+        if correspondence.position.current == correspondence.seat {
+            correspondence.file.push(slot.index, &correspondence.key);
+            moved = true;
+        }
+
+        correspondence
+            .position
+            .play(slot.index, correspondence.file.mode);
+    }
+
+    let turn_over =
+        correspondence.position.current != correspondence.seat || correspondence.position.is_over();
+
+    if moved && turn_over {
+        correspondence.saved = Some(save(&correspondence.file, &inbox(&config_dir)));
+        // only once it's sent, since the opponent never sees a turn we didn't finish
+        Seen::record(&mut seen, &correspondence.file);
+    }
+}
+
+fn save(file: &GameFile, inbox: &Path) -> Result<PathBuf, String> {
+    let path = inbox.join(file.file_name());
+
+    fs::create_dir_all(inbox)
+        .and_then(|_| fs::write(&path, file.to_string()))
+        .map_err(|error| format!("CAN'T SAVE: {}", error.kind()))?;
+
+    Ok(path)
+}
+
+fn update_banner(
+    mut commands: Commands,
+    mut text_query: Query<&mut Text, With<Banner>>,
+    correspondence: Res<Correspondence>,
+    current_player: Res<CurrentPlayer>,
+    ui_assets: Res<UiAssets>,
+) {
+    if !(correspondence.is_changed() || current_player.is_changed()) {
+        return;
+    }
+
+    let value = match &correspondence.saved {
+        Some(Ok(path)) => format!(
+            "SAVED {}, SEND IT TO {}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            correspondence.opponent()
+        ),
+        Some(Err(error)) => error.clone(),
+        None if correspondence.is_our_turn(current_player.0) => "YOUR MOVE".to_string(),
+        None => format!("{} TO MOVE", correspondence.opponent()),
+    };
+
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[0].value = value;
+        return;
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Px(20.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            Banner,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    value,
+                    TextStyle {
+                        font: ui_assets.font.clone(),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                Banner,
+            ));
+        });
+}
+
+fn end(mut commands: Commands) {
+    commands.remove_resource::<Correspondence>();
+}
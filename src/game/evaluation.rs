@@ -0,0 +1,306 @@
+use super::{
+    ai::{
+        search::{Search, TranspositionTable},
+        AiPlayers, Difficulty,
+    },
+    attract::Attract,
+    board::BOARD_HEIGHT,
+    chart::{PLAYER_1_COLOR, PLAYER_2_COLOR},
+    helpers,
+    rules::Position,
+    Board, CurrentPlayer, Player, Slot, StartingPlayer, TurnEndEvent,
+};
+use crate::{
+    states::{AppState, GameMode},
+    ui::UiAssets,
+};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+
+/// The evaluation looks as far ahead as the Hard CPU, short of Perfect, so it keeps up with play.
+const EVALUATION_DIFFICULTY: Difficulty = Difficulty::Hard;
+/// How far ahead, in seeds, fills the bar completely.
+const EVALUATION_RANGE: f32 = 12.;
+const BAR_WIDTH: f32 = 16.;
+
+pub struct EvaluationPlugin;
+
+impl Plugin for EvaluationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowEvaluation>()
+            .add_systems(OnEnter(AppState::Game), setup)
+            .add_systems(
+                Update,
+                (
+                    request.run_if(on_event::<TurnEndEvent>()),
+                    receive.run_if(resource_exists::<EvaluationTask>),
+                    button_action,
+                    update_visibility,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                OnExit(AppState::Game),
+                (
+                    helpers::despawn::<EvaluationBar>,
+                    helpers::despawn::<EvaluationToggle>,
+                    end,
+                ),
+            );
+    }
+}
+
+/// Whether the bar is showing. Off by default against the CPU, so it isn't leaned on.
+#[derive(Resource, Default)]
+struct ShowEvaluation(bool);
+
+/// The search running in the background, scoring the board in seeds for player one.
+#[derive(Resource)]
+struct EvaluationTask(Task<i32>);
+
+#[derive(Component)]
+struct EvaluationBar;
+
+#[derive(Component)]
+struct EvaluationFill;
+
+#[derive(Component)]
+struct EvaluationText;
+
+#[derive(Component)]
+struct EvaluationToggle;
+
+fn label(show: &ShowEvaluation) -> String {
+    format!("EVAL: {}", if show.0 { "ON" } else { "OFF" })
+}
+
+/// Scores `position` in seeds for player one, however many moves the search sees ahead.
+fn evaluate(position: &Position, game_mode: GameMode) -> i32 {
+    let depth = EVALUATION_DIFFICULTY.depth().unwrap_or(1);
+    let mut table = TranspositionTable::default();
+    let score =
+        Search::new(game_mode, None, &mut table).negamax(position, depth, -i32::MAX, i32::MAX);
+
+    match position.current {
+        Player::One => score,
+        Player::Two => -score,
+    }
+}
+
+fn spawn_task(commands: &mut Commands, position: Position, game_mode: GameMode) {
+    let task = AsyncComputeTaskPool::get().spawn(async move { evaluate(&position, game_mode) });
+
+    // replacing an older task drops it, which cancels it
+    commands.insert_resource(EvaluationTask(task));
+}
+
+fn setup(
+    mut commands: Commands,
+    mut show: ResMut<ShowEvaluation>,
+    ai_players: Res<AiPlayers>,
+    attract: Option<Res<Attract>>,
+    starting_player: Res<StartingPlayer>,
+    game_mode: Res<State<GameMode>>,
+    ui_assets: Res<UiAssets>,
+) {
+    // the demo is only there to be looked at
+    if attract.is_some() {
+        return;
+    }
+
+    show.0 = ai_players.human().is_none();
+
+    spawn_task(
+        &mut commands,
+        Position::new(starting_player.0),
+        *game_mode.get(),
+    );
+
+    let style = TextStyle {
+        font: ui_assets.font.clone(),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(40.),
+                    top: Val::Percent(50.),
+                    margin: UiRect::top(Val::Px(-BOARD_HEIGHT / 2.)),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            EvaluationBar,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(BOARD_HEIGHT),
+                        ..default()
+                    },
+                    background_color: PLAYER_2_COLOR.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    // player one's share, from the top like their label
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(50.),
+                                ..default()
+                            },
+                            background_color: PLAYER_1_COLOR.into(),
+                            ..default()
+                        },
+                        EvaluationFill,
+                    ));
+                });
+
+            parent.spawn((TextBundle::from_section("0", style.clone()), EvaluationText));
+        });
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.),
+                    top: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            EvaluationToggle,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(ButtonBundle {
+                    background_color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(label(&show), style));
+                });
+        });
+}
+
+fn request(
+    mut commands: Commands,
+    slot_query: Query<&Slot>,
+    current_player: Res<CurrentPlayer>,
+    game_mode: Res<State<GameMode>>,
+    bar_query: Query<(), With<EvaluationBar>>,
+) {
+    if bar_query.is_empty() {
+        return;
+    }
+
+    let mut position = Position {
+        counts: [0; Board::LENGTH],
+        current: current_player.0,
+    };
+
+    for slot in &slot_query {
+        position.counts[slot.index] = slot.count;
+    }
+
+    spawn_task(&mut commands, position, *game_mode.get());
+}
+
+fn receive(
+    mut commands: Commands,
+    mut task: ResMut<EvaluationTask>,
+    mut fill_query: Query<&mut Style, With<EvaluationFill>>,
+    mut text_query: Query<&mut Text, With<EvaluationText>>,
+) {
+    let Some(score) = block_on(future::poll_once(&mut task.0)) else {
+        return;
+    };
+
+    commands.remove_resource::<EvaluationTask>();
+
+    let share = 0.5 + (score as f32 / EVALUATION_RANGE).clamp(-1., 1.) / 2.;
+
+    for mut style in &mut fill_query {
+        style.height = Val::Percent(share * 100.);
+    }
+
+    for mut text in &mut text_query {
+        let section = &mut text.sections[0];
+
+        section.value = format!("{}", score.abs());
+        section.style.color = match score.signum() {
+            1 => PLAYER_1_COLOR,
+            -1 => PLAYER_2_COLOR,
+            _ => Color::WHITE,
+        };
+    }
+}
+
+fn button_action(
+    interaction_query: Query<(&Children, &Interaction, &Parent), Changed<Interaction>>,
+    toggle_query: Query<(), With<EvaluationToggle>>,
+    mut text_query: Query<&mut Text>,
+    mut show: ResMut<ShowEvaluation>,
+) {
+    for (children, interaction, parent) in &interaction_query {
+        if !toggle_query.contains(parent.get()) {
+            continue;
+        }
+
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+
+        match interaction {
+            Interaction::Pressed => {
+                show.0 = !show.0;
+
+                // still hovered, so keep the arrows
+                text.sections[0].value = format!("> {} <", label(&show));
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = format!("> {} <", label(&show));
+            }
+            Interaction::None => {
+                text.sections[0].value = label(&show);
+            }
+        }
+    }
+}
+
+fn update_visibility(
+    mut bar_query: Query<&mut Visibility, With<EvaluationBar>>,
+    show: Res<ShowEvaluation>,
+) {
+    if !show.is_changed() {
+        return;
+    }
+
+    for mut visibility in &mut bar_query {
+        *visibility = if show.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn end(mut commands: Commands) {
+    commands.remove_resource::<EvaluationTask>();
+}
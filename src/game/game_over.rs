@@ -1,11 +1,21 @@
-use super::{animation::bezier_blend, helpers, GameState, Winner};
+use super::{
+    animation::bezier_blend,
+    chart::{self, ScoreHistory},
+    correspondence::Correspondence,
+    helpers,
+    network::Session,
+    Board, EndReason, GameRecord, GameState, Player, Slot, StartingPlayer, Winner,
+};
 use crate::{states::AppState, ui::UiAssets};
 use bevy::prelude::*;
+use std::fmt;
 
 pub struct GameOverPlugin;
 
 const ALPHA_END: f32 = 0.7;
 const ALPHA_SPEED: f32 = 5.0;
+const STAT_LABEL_WIDTH: f32 = 280.;
+const STAT_VALUE_WIDTH: f32 = 160.;
 
 impl Plugin for GameOverPlugin {
     fn build(&self, app: &mut App) {
@@ -18,7 +28,12 @@ impl Plugin for GameOverPlugin {
                 (fade.run_if(in_state(GameOverState::Hidden)), button_action)
                     .run_if(in_state(GameState::Over)),
             )
-            .add_systems(OnEnter(GameOverState::Visible), show);
+            .add_systems(OnEnter(GameOverState::Visible), show)
+            .add_systems(
+                OnEnter(GameOverState::Analysing),
+                set_display(Display::None),
+            )
+            .add_systems(OnExit(GameOverState::Analysing), set_display(Display::Flex));
     }
 }
 
@@ -31,11 +46,25 @@ struct GameOverContainer;
 #[derive(Component)]
 struct GameOverElement;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 enum GameOverButtonAction {
+    Rematch,
+    SwapRematch,
+    Analyse,
     Menu,
 }
 
+impl fmt::Display for GameOverButtonAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Rematch => write!(f, "REMATCH"),
+            Self::SwapRematch => write!(f, "REMATCH, SWAP FIRST"),
+            Self::Analyse => write!(f, "ANALYSE"),
+            Self::Menu => write!(f, "MENU"),
+        }
+    }
+}
+
 #[derive(Resource, Default)]
 struct GameOverAlpha {
     value: f32,
@@ -47,12 +76,21 @@ pub enum GameOverState {
     #[default]
     Hidden,
     Visible,
+    /// Stepping through the game's mistakes, with the board showing.
+    Analysing,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn setup(
     mut commands: Commands,
     winner: Res<Winner>,
+    record: Res<GameRecord>,
+    history: Res<ScoreHistory>,
+    board: Res<Board>,
+    slot_query: Query<&Slot>,
     ui_assets: Res<UiAssets>,
+    session: Option<Res<Session>>,
+    correspondence: Option<Res<Correspondence>>,
     mut alpha: ResMut<GameOverAlpha>,
     mut state: ResMut<NextState<GameOverState>>,
 ) {
@@ -60,10 +98,14 @@ fn setup(
 
     commands.entity(screen).insert(GameOverScreen);
 
-    let value = winner.0.as_ref().map_or_else(
-        || "Draw!".to_string(),
-        |&player| format!("{} WINS!", player.to_string()),
-    );
+    let value = match (winner.player, winner.reason) {
+        (None, EndReason::Agreement) => "DRAW BY AGREEMENT".to_string(),
+        (None, _) => "Draw!".to_string(),
+        (Some(player), EndReason::Resignation) => format!("{player} WINS BY RESIGNATION"),
+        (Some(player), EndReason::Timeout) => format!("{player} WINS ON TIME"),
+        (Some(player), EndReason::Disconnect) => format!("{player} WINS, OPPONENT LEFT"),
+        (Some(player), _) => format!("{player} WINS!"),
+    };
 
     let container = commands
         .spawn((
@@ -103,34 +145,137 @@ fn setup(
         ))
         .id();
 
-    let button = commands
+    let mut stores = [0; 2];
+
+    for player in Player::iter() {
+        let store = slot_query
+            .get(board.slots[Board::get_store(player)])
+            .unwrap();
+        stores[player as usize] = store.count + record.swept[player as usize];
+    }
+
+    let rows: [(String, Vec<String>); 6] = [
+        (
+            String::new(),
+            Player::iter().map(|player| player.to_string()).collect(),
+        ),
+        (
+            "STORE".to_string(),
+            stores.iter().map(u32::to_string).collect(),
+        ),
+        (
+            "SWEPT".to_string(),
+            record.swept.iter().map(u32::to_string).collect(),
+        ),
+        (
+            "CAPTURES".to_string(),
+            Player::iter()
+                .map(|player| record.captures(player).to_string())
+                .collect(),
+        ),
+        (
+            "EXTRA TURNS".to_string(),
+            Player::iter()
+                .map(|player| record.extra_turns(player).to_string())
+                .collect(),
+        ),
+        (
+            "HINTS".to_string(),
+            record.hints.iter().map(u32::to_string).collect(),
+        ),
+    ];
+
+    let stats = commands
         .spawn((
-            ButtonBundle {
+            NodeBundle {
                 style: Style {
-                    justify_content: JustifyContent::Center,
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Row,
                     align_items: AlignItems::Center,
+                    column_gap: Val::Px(40.),
                     ..default()
                 },
                 visibility: Visibility::Hidden,
-                background_color: Color::NONE.into(),
                 ..default()
             },
             GameOverElement,
-            GameOverButtonAction::Menu,
         ))
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(
-                "PLAY AGAIN",
-                TextStyle {
-                    font: ui_assets.font.clone(),
-                    font_size: 40.0,
-                    color: Color::WHITE,
-                },
-            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (label, values) in rows {
+                        spawn_stat_row(parent, &ui_assets, &label, &values);
+                    }
+
+                    parent.spawn(TextBundle::from_section(
+                        format!("{} MOVES", record.moves.len()),
+                        TextStyle {
+                            font: ui_assets.font.clone(),
+                            font_size: 40.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+
+            chart::spawn_chart(parent, &ui_assets, &history, stores);
         })
         .id();
 
-    commands.entity(container).push_children(&[text, button]);
+    // a network or correspondence game has nobody here to rematch
+    let actions = if session.is_some() || correspondence.is_some() {
+        vec![GameOverButtonAction::Analyse, GameOverButtonAction::Menu]
+    } else {
+        vec![
+            GameOverButtonAction::Rematch,
+            GameOverButtonAction::SwapRematch,
+            GameOverButtonAction::Analyse,
+            GameOverButtonAction::Menu,
+        ]
+    };
+
+    let buttons: Vec<Entity> = actions
+        .into_iter()
+        .map(|action| {
+            commands
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        visibility: Visibility::Hidden,
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    },
+                    GameOverElement,
+                    action,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        action.to_string(),
+                        TextStyle {
+                            font: ui_assets.font.clone(),
+                            font_size: 40.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                })
+                .id()
+        })
+        .collect();
+
+    commands.entity(container).push_children(&[text, stats]);
+    commands.entity(container).push_children(&buttons);
     commands.entity(screen).add_child(container);
 
     alpha.value = 0.;
@@ -139,6 +284,52 @@ fn setup(
     state.set(GameOverState::Hidden);
 }
 
+fn spawn_stat_row(parent: &mut ChildBuilder, ui_assets: &UiAssets, label: &str, values: &[String]) {
+    let style = TextStyle {
+        font: ui_assets.font.clone(),
+        font_size: 40.0,
+        color: Color::WHITE,
+    };
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Flex,
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(STAT_LABEL_WIDTH),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(label, style.clone()));
+                });
+
+            for value in values {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(STAT_VALUE_WIDTH),
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(value, style.clone()));
+                    });
+            }
+        });
+}
+
 fn fade(
     mut background_color_query: Query<&mut BackgroundColor, With<GameOverContainer>>,
     time: Res<Time>,
@@ -164,23 +355,61 @@ fn show(mut visibility_query: Query<&mut Visibility, With<GameOverElement>>) {
     }
 }
 
+/// Hides or shows the game over screen without losing what's on it.
+fn set_display(display: Display) -> impl FnMut(Query<&mut Style, With<GameOverContainer>>) {
+    move |mut style_query| {
+        for mut style in &mut style_query {
+            style.display = display;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn button_action(
     interaction_query: Query<
-        (&Interaction, &GameOverButtonAction),
+        (&Children, &Interaction, &GameOverButtonAction),
         (Changed<Interaction>, With<Button>),
     >,
+    mut text_query: Query<&mut Text>,
     mut app_state: ResMut<NextState<AppState>>,
     mut game_state: ResMut<NextState<GameState>>,
+    mut starting_player: ResMut<StartingPlayer>,
+    mut state: ResMut<NextState<GameOverState>>,
 ) {
-    for (interaction, action) in &interaction_query {
+    for (children, interaction, action) in &interaction_query {
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+
         match interaction {
-            Interaction::Pressed => match *action {
-                GameOverButtonAction::Menu => {
-                    app_state.set(AppState::Menu);
-                    game_state.set(GameState::None);
+            Interaction::Pressed => {
+                match *action {
+                    GameOverButtonAction::Analyse => {
+                        // the game isn't left, only the screen is put aside
+                        text.sections[0].value = action.to_string();
+                        state.set(GameOverState::Analysing);
+                        continue;
+                    }
+                    GameOverButtonAction::Rematch => {
+                        app_state.set(AppState::Restart);
+                    }
+                    GameOverButtonAction::SwapRematch => {
+                        starting_player.0 = starting_player.0.flip();
+                        app_state.set(AppState::Restart);
+                    }
+                    GameOverButtonAction::Menu => {
+                        app_state.set(AppState::Menu);
+                    }
                 }
-            },
-            _ => {}
+
+                game_state.set(GameState::None);
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = format!("> {action} <");
+            }
+            Interaction::None => {
+                text.sections[0].value = action.to_string();
+            }
         }
     }
 }
@@ -105,9 +105,7 @@ pub fn get_label<T: Component>(
         .id()
 }
 
-pub fn random_point_in_circle(radius: Vec2) -> Vec2 {
-    let mut rng = rand::thread_rng();
-
+pub fn random_point_in_circle(radius: Vec2, rng: &mut impl Rng) -> Vec2 {
     let theta = rng.gen_range(0.0..2.0 * std::f32::consts::PI);
     let rx = rng.gen_range(0.0..(radius.x));
     let ry = rng.gen_range(0.0..(radius.y));
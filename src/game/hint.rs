@@ -0,0 +1,357 @@
+use super::{
+    ai::{
+        endgame::Endgames,
+        search::{Search, TranspositionTable},
+        AiPlayers, Difficulty,
+    },
+    board::{SlotHoverEvent, SlotPressEvent},
+    correspondence::Correspondence,
+    helpers,
+    marble::MarbleOutlineEvent,
+    network::Session,
+    rules::Position,
+    Board, CurrentPlayer, GameRecord, GameState, Player, Slot,
+};
+use crate::{
+    states::{AppState, GameMode},
+    ui::UiAssets,
+};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+
+/// Hints look as far ahead as the strongest CPU does, endgame table and all.
+const HINT_DIFFICULTY: Difficulty = Difficulty::Perfect;
+
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hint>()
+            .init_resource::<HintTable>()
+            .add_systems(OnEnter(AppState::Game), setup)
+            .add_systems(
+                Update,
+                (
+                    button_action,
+                    receive.run_if(resource_exists::<HintTask>),
+                    clear,
+                    keep_outline,
+                    update_visibility,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                OnExit(AppState::Game),
+                (helpers::despawn::<HintControl>, reset),
+            );
+    }
+}
+
+/// The pit last recommended, outlined until a move is played.
+#[derive(Resource, Default)]
+struct Hint(Option<Entity>);
+
+/// Kept from one hint to the next, and lent to the search while it runs.
+#[derive(Resource, Default)]
+struct HintTable(TranspositionTable);
+
+/// What a hint search was asked about, and what it found.
+struct HintSearch {
+    player: Player,
+    position: Position,
+    game_mode: GameMode,
+    best: Option<(usize, i32)>,
+    table: TranspositionTable,
+}
+
+/// The search running in the background. Only present until it's done, hiding the button.
+#[derive(Resource)]
+struct HintTask(Task<HintSearch>);
+
+#[derive(Component)]
+struct HintControl;
+
+#[derive(Component)]
+struct HintButton;
+
+#[derive(Component)]
+struct HintReason;
+
+const LABEL: &str = "HINT";
+
+fn setup(mut commands: Commands, ai_players: Res<AiPlayers>, ui_assets: Res<UiAssets>) {
+    // nobody to give a hint to
+    if ai_players.is_spectating() {
+        return;
+    }
+
+    let style = TextStyle {
+        font: ui_assets.font.clone(),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(20.),
+                    bottom: Val::Px(20.),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::End,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            HintControl,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section("", style.clone()), HintReason));
+
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            display: Display::Flex,
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(8.),
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    },
+                    HintButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(LABEL, style));
+                    parent.spawn(ImageBundle {
+                        style: Style {
+                            width: Val::Px(32.),
+                            aspect_ratio: Some(1.0),
+                            ..default()
+                        },
+                        image: ui_assets.info.clone().into(),
+                        ..default()
+                    });
+                });
+        });
+}
+
+/// Whether the player to move is a person at this machine, free to move right now.
+fn can_hint(
+    ai_players: &AiPlayers,
+    current_player: &CurrentPlayer,
+    session: Option<&Session>,
+    correspondence: Option<&Correspondence>,
+) -> bool {
+    let player = current_player.0;
+
+    !ai_players.controls(player)
+        && session.is_none_or(|session| {
+            session.seat == Some(player) && !session.awaiting_reply && !session.waiting
+        })
+        && correspondence.is_none_or(|correspondence| correspondence.is_our_turn(player))
+}
+
+/// Why a move is worth playing, in a few words.
+fn reason(position: &Position, index: usize, score: i32, game_mode: GameMode) -> String {
+    if position.legal_moves().count() == 1 {
+        return "ONLY MOVE".to_string();
+    }
+
+    let mut after = position.clone();
+    let outcome = after.play(index, game_mode);
+
+    if outcome.extra_turn {
+        "EXTRA TURN".to_string()
+    } else if outcome.captured > 0 {
+        format!("CAPTURES {}", outcome.captured)
+    } else if score > 0 {
+        format!("AHEAD BY {score} WITH BEST PLAY")
+    } else {
+        "SAFEST MOVE".to_string()
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn button_action(
+    mut commands: Commands,
+    interaction_query: Query<
+        (&Children, &Interaction),
+        (Changed<Interaction>, With<Button>, With<HintButton>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut table: ResMut<HintTable>,
+    hint: Res<Hint>,
+    task: Option<Res<HintTask>>,
+    slot_query: Query<&Slot>,
+    current_player: Res<CurrentPlayer>,
+    game_mode: Res<State<GameMode>>,
+    endgames: Res<Endgames>,
+) {
+    for (children, interaction) in &interaction_query {
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+
+        match interaction {
+            Interaction::Pressed => {
+                // still hovered, so keep the arrows
+                text.sections[0].value = format!("> {LABEL} <");
+
+                if hint.0.is_some() || task.is_some() {
+                    continue;
+                }
+
+                let mut position = Position {
+                    counts: [0; Board::LENGTH],
+                    current: current_player.0,
+                };
+
+                for slot in &slot_query {
+                    position.counts[slot.index] = slot.count;
+                }
+
+                let game_mode = *game_mode.get();
+                let endgame = endgames.get(game_mode);
+                let mut table = std::mem::take(&mut table.0);
+
+                // searching this deep takes too long to do between frames
+                let task = AsyncComputeTaskPool::get().spawn(async move {
+                    let depth = HINT_DIFFICULTY.depth().unwrap_or(1);
+                    let best = Search::new(game_mode, endgame.as_deref(), &mut table)
+                        .best_move(&position, depth);
+
+                    HintSearch {
+                        player: position.current,
+                        position,
+                        game_mode,
+                        best,
+                        table,
+                    }
+                });
+
+                commands.insert_resource(HintTask(task));
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = format!("> {LABEL} <");
+            }
+            Interaction::None => {
+                text.sections[0].value = LABEL.to_string();
+            }
+        }
+    }
+}
+
+/// Outlines the pit the search settled on, once it's done.
+#[allow(clippy::too_many_arguments)]
+fn receive(
+    mut commands: Commands,
+    mut task: ResMut<HintTask>,
+    mut table: ResMut<HintTable>,
+    mut hint: ResMut<Hint>,
+    mut record: ResMut<GameRecord>,
+    mut marble_outline_events: EventWriter<MarbleOutlineEvent>,
+    mut reason_query: Query<&mut Text, With<HintReason>>,
+    board: Res<Board>,
+) {
+    let Some(search) = block_on(future::poll_once(&mut task.0)) else {
+        return;
+    };
+
+    commands.remove_resource::<HintTask>();
+    table.0 = search.table;
+
+    let Some((index, score)) = search.best else {
+        return;
+    };
+
+    hint.0 = Some(board.slots[index]);
+    record.hints[search.player as usize] += 1;
+    marble_outline_events.send(MarbleOutlineEvent(board.slots[index], Visibility::Visible));
+
+    if let Ok(mut text) = reason_query.get_single_mut() {
+        text.sections[0].value = reason(&search.position, index, score, search.game_mode);
+    }
+}
+
+/// Forgets the hint once any move is played, along with a search for it still under way.
+fn clear(
+    mut commands: Commands,
+    mut hint: ResMut<Hint>,
+    mut slot_press_events: EventReader<SlotPressEvent>,
+    mut marble_outline_events: EventWriter<MarbleOutlineEvent>,
+    mut reason_query: Query<&mut Text, With<HintReason>>,
+) {
+    if slot_press_events.read().count() == 0 {
+        return;
+    }
+
+    // dropping the task cancels it
+    commands.remove_resource::<HintTask>();
+
+    if let Some(slot) = hint.0.take() {
+        marble_outline_events.send(MarbleOutlineEvent(slot, Visibility::Hidden));
+    }
+
+    if let Ok(mut text) = reason_query.get_single_mut() {
+        text.sections[0].value.clear();
+    }
+}
+
+/// Hovering off the hinted pit would otherwise hide its outline.
+fn keep_outline(
+    hint: Res<Hint>,
+    mut slot_hover_events: EventReader<SlotHoverEvent>,
+    mut marble_outline_events: EventWriter<MarbleOutlineEvent>,
+) {
+    let Some(hinted) = hint.0 else {
+        slot_hover_events.clear();
+        return;
+    };
+
+    for SlotHoverEvent(slot, hovered) in slot_hover_events.read() {
+        if *slot == hinted && !hovered {
+            marble_outline_events.send(MarbleOutlineEvent(hinted, Visibility::Visible));
+        }
+    }
+}
+
+fn update_visibility(
+    mut control_query: Query<&mut Visibility, With<HintControl>>,
+    game_state: Res<State<GameState>>,
+    ai_players: Res<AiPlayers>,
+    current_player: Res<CurrentPlayer>,
+    session: Option<Res<Session>>,
+    correspondence: Option<Res<Correspondence>>,
+    task: Option<Res<HintTask>>,
+) {
+    let visible = *game_state.get() == GameState::Idle
+        && task.is_none()
+        && can_hint(
+            &ai_players,
+            &current_player,
+            session.as_deref(),
+            correspondence.as_deref(),
+        );
+
+    for mut visibility in &mut control_query {
+        *visibility = if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn reset(mut commands: Commands, mut hint: ResMut<Hint>) {
+    hint.0 = None;
+    commands.remove_resource::<HintTask>();
+}
@@ -1,6 +1,6 @@
 use super::{
     helpers,
-    marble::{MarbleStack, MarbleStackEntity},
+    marble::{MarbleContainer, MarbleStack, MarbleStackEntity},
     Board, Slot,
 };
 use crate::{states::AppState, ui::UiAssets};
@@ -30,8 +30,9 @@ pub struct Label(Entity);
 pub fn draw_labels(
     mut commands: Commands,
     label_query: Query<Entity, With<Label>>,
-    marble_stack_query: Query<&MarbleStack>,
-    container_query: Query<Entity, Added<MarbleStack>>,
+    marble_stack_query: Query<&MarbleStack, With<MarbleContainer>>,
+    // stacks carrying marbles in a long avalanche can add up to as many as the board has slots
+    container_query: Query<Entity, (Added<MarbleStack>, With<MarbleContainer>)>,
     slot_query: Query<&Slot>,
     assets: Res<UiAssets>,
 ) {
@@ -88,7 +89,7 @@ pub fn draw_labels(
         }
     }
 
-    store_labels.sort_by(|a, b| a.1.cmp(&b.1));
+    store_labels.sort_by_key(|label| label.1);
 
     slot_labels.sort_by(|a, b| {
         let ord_a = Board::slot_order().iter().position(|&x| x == a.1).unwrap();
@@ -1,4 +1,8 @@
-use super::{board::SlotUi, helpers};
+use super::{
+    board::SlotUi,
+    helpers,
+    rng::{GameRng, Stream},
+};
 use crate::{game::Slot, states::AppState, ui::UiAssets};
 use bevy::{
     ecs::system::SystemParam,
@@ -58,8 +62,9 @@ pub struct Marble;
 #[derive(Component)]
 pub struct MarbleStack(pub Entity, pub Vec2, pub Vec2);
 
+/// The stack a slot's marbles rest in, as opposed to one carrying them across the board.
 #[derive(Component)]
-struct MarbleContainer;
+pub struct MarbleContainer;
 
 #[derive(Component)]
 pub struct MarbleOutline(Entity);
@@ -81,6 +86,7 @@ impl Material2d for OutlineMaterial {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_marble_events(
     mut commands: Commands,
     mut marble_events: EventReader<MarbleEvent>,
@@ -89,6 +95,7 @@ pub fn handle_marble_events(
     mut materials: ResMut<Assets<OutlineMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
     ui_assets: Res<UiAssets>,
+    mut rng: ResMut<GameRng>,
 ) {
     for MarbleEvent(event) in marble_events.read() {
         match event {
@@ -99,7 +106,12 @@ pub fn handle_marble_events(
                     let offset = offset
                         .as_ref()
                         .map_or_else(
-                            || helpers::random_point_in_circle(stack.2),
+                            || {
+                                helpers::random_point_in_circle(
+                                    stack.2,
+                                    rng.stream(Stream::Marbles),
+                                )
+                            },
                             |offset| offset.clamp_length_max(stack.2.y),
                         )
                         .extend(0.);
@@ -162,6 +174,31 @@ pub fn handle_marble_events(
     }
 }
 
+/// Swaps every slot's marbles for `counts`, without animating, once the stacks are on the board.
+/// Returns whether it could.
+pub fn restack(
+    counts: &[u32],
+    slot_query: &mut Query<(Entity, &mut Slot)>,
+    stack_query: &Query<&MarbleStack>,
+    marble_events: &mut EventWriter<MarbleEvent>,
+) -> bool {
+    if stack_query.iter().count() != counts.len() {
+        return false;
+    }
+
+    for (entity, mut slot) in slot_query.iter_mut() {
+        let count = counts[slot.index];
+
+        if slot.count != count {
+            marble_events.send(MarbleEvent(MarbleEventKind::Del((entity, slot.count))));
+            marble_events.send(MarbleEvent(MarbleEventKind::Add((entity, count, None))));
+            slot.count = count;
+        }
+    }
+
+    true
+}
+
 pub fn handle_marble_outline(
     mut outline_query: Query<(&MarbleOutline, &mut Visibility)>,
     mut marble_outline_events: EventReader<MarbleOutlineEvent>,
@@ -183,11 +220,14 @@ pub fn draw_containers(
     mut marble_events: EventWriter<MarbleEvent>,
 ) {
     for (style, global_transform, slot_ui) in global_transform_query.iter() {
-        let (camera, camera_transform) = camera_query.get_single().unwrap();
-
-        let transform = camera
-            .viewport_to_world_2d(camera_transform, global_transform.translation().xy())
-            .unwrap();
+        // without a camera to place it by, as when running headless, the stack sits at the origin
+        let transform = camera_query
+            .get_single()
+            .ok()
+            .and_then(|(camera, camera_transform)| {
+                camera.viewport_to_world_2d(camera_transform, global_transform.translation().xy())
+            })
+            .unwrap_or_default();
 
         let radius = {
             // width and height are guaranteed to be Val::Px here
@@ -1,19 +1,32 @@
-use self::animation::AnimationState;
+pub use self::{animation::AnimationState, board::SlotPressEvent};
 use crate::{
     states::{AppState, GameMode},
     ui::ReloadUiEvent,
 };
 use bevy::prelude::*;
-use board::SlotPressEvent;
-use std::{cmp::Ordering, collections::VecDeque, ops::Range};
+use rules::Position;
+use std::{collections::VecDeque, fmt, ops::Range};
 
 pub mod ai;
+pub mod analysis;
 mod animation;
+mod attract;
 mod board;
+mod chart;
+pub mod clock;
+pub mod correspondence;
+mod evaluation;
 mod game_over;
 mod helpers;
+mod hint;
 mod label;
 mod marble;
+pub mod network;
+mod pause;
+mod resign;
+pub mod rng;
+pub mod rules;
+mod speed;
 mod turn_indicator;
 
 const SLOT_START_AMOUNT: u32 = 6;
@@ -25,20 +38,37 @@ impl Plugin for GamePlugin {
         app.add_plugins((
             ai::AiPlugin,
             animation::AnimationPlugin,
+            attract::AttractPlugin,
             board::BoardPlugin,
+            chart::ChartPlugin,
+            clock::ClockPlugin,
+            correspondence::CorrespondencePlugin,
             game_over::GameOverPlugin,
             label::LabelPlugin,
             marble::MarblePlugin,
+            network::NetworkPlugin,
+            pause::PausePlugin,
+            resign::ResignPlugin,
+            speed::SpeedPlugin,
             turn_indicator::TurnIndicatorPlugin,
         ))
+        .add_plugins((
+            analysis::AnalysisPlugin,
+            evaluation::EvaluationPlugin,
+            hint::HintPlugin,
+            rng::RngPlugin,
+        ))
         .init_state::<GameState>()
         .init_resource::<CurrentPlayer>()
         .init_resource::<Board>()
         .init_resource::<Winner>()
+        .init_resource::<StartingPlayer>()
+        .init_resource::<GameRecord>()
         .add_event::<MoveEvent>()
         .add_event::<CaptureEvent>()
         .add_event::<TurnEndEvent>()
         .add_systems(OnEnter(AppState::Game), setup_slots)
+        .add_systems(OnEnter(AppState::Restart), restart)
         .add_systems(Update, (handle_move).run_if(in_state(AppState::Game)))
         .add_systems(
             OnEnter(AnimationState::Animating),
@@ -60,7 +90,7 @@ impl Plugin for GamePlugin {
     }
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Player {
     #[default]
     One,
@@ -80,30 +110,15 @@ impl Player {
     }
 }
 
-impl PartialEq for Player {
-    fn eq(&self, other: &Self) -> bool {
-        matches!(
-            (self, other),
-            (Self::One, Self::One) | (Self::Two, Self::Two)
-        )
-    }
-}
-
-impl ToString for Player {
-    fn to_string(&self) -> String {
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::One => "PLAYER 1".to_string(),
-            Self::Two => "PLAYER 2".to_string(),
+            Self::One => write!(f, "PLAYER 1"),
+            Self::Two => write!(f, "PLAYER 2"),
         }
     }
 }
 
-enum MoveEndAction {
-    Repeat,
-    Continue,
-    End,
-}
-
 #[derive(Event)]
 pub struct MoveEvent(pub VecDeque<Entity>);
 
@@ -128,11 +143,9 @@ struct BoardElement;
 #[derive(Resource, Default, Debug)]
 pub struct CurrentPlayer(pub Player);
 
-impl CurrentPlayer {
-    pub fn flip(&mut self) {
-        self.0 = self.0.flip();
-    }
-}
+/// The player who moves first when a game is set up.
+#[derive(Resource, Default, Debug)]
+pub struct StartingPlayer(pub Player);
 
 #[derive(Resource, Default)]
 pub struct Board {
@@ -180,8 +193,65 @@ impl Board {
             .collect()
     }
 }
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndReason {
+    #[default]
+    Score,
+    Resignation,
+    Agreement,
+    Timeout,
+    /// The other side of a network game went away.
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveRecord {
+    pub player: Player,
+    /// The pit played.
+    pub index: usize,
+    pub captured: u32,
+    pub extra_turn: bool,
+}
+
+/// Everything that happened in the current game, in order.
 #[derive(Resource, Default)]
-pub struct Winner(Option<Player>);
+pub struct GameRecord {
+    pub moves: Vec<MoveRecord>,
+    /// Seeds moved into each player's store by `capture_side` when the game ended.
+    pub swept: [u32; 2],
+    /// How many hints each player asked for.
+    pub hints: [u32; 2],
+}
+
+impl GameRecord {
+    pub fn from_records(records: &[(MoveRecord, [u32; 2])]) -> Self {
+        Self {
+            moves: records.iter().map(|(record, _)| *record).collect(),
+            ..default()
+        }
+    }
+
+    pub fn captures(&self, player: Player) -> usize {
+        self.moves
+            .iter()
+            .filter(|record| record.player == player && record.captured > 0)
+            .count()
+    }
+
+    pub fn extra_turns(&self, player: Player) -> usize {
+        self.moves
+            .iter()
+            .filter(|record| record.player == player && record.extra_turn)
+            .count()
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Winner {
+    pub player: Option<Player>,
+    pub reason: EndReason,
+}
 
 #[derive(States, SystemSet, Debug, Hash, PartialEq, Eq, Clone, Default)]
 pub enum GameState {
@@ -192,6 +262,7 @@ pub enum GameState {
     Over,
 }
 
+#[allow(clippy::too_many_arguments)]
 fn setup_slots(
     mut commands: Commands,
     mut game_state: ResMut<NextState<GameState>>,
@@ -199,6 +270,8 @@ fn setup_slots(
     mut reload_ui_event: EventWriter<ReloadUiEvent>,
     mut board: ResMut<Board>,
     mut current_player: ResMut<CurrentPlayer>,
+    mut record: ResMut<GameRecord>,
+    starting_player: Res<StartingPlayer>,
 ) {
     let board_texture = asset_server.load("textures/board.png");
 
@@ -216,7 +289,8 @@ fn setup_slots(
     ));
 
     board.slots.clear();
-    current_player.0 = Player::default();
+    current_player.0 = starting_player.0;
+    *record = GameRecord::default();
 
     for index in 0..Board::LENGTH {
         let slot = Slot {
@@ -237,191 +311,106 @@ fn setup_slots(
     reload_ui_event.send_default();
 }
 
+fn restart(mut app_state: ResMut<NextState<AppState>>) {
+    app_state.set(AppState::Game);
+}
+
+#[allow(clippy::too_many_arguments)]
 fn handle_move(
     board: Res<Board>,
     game_mode: Res<State<GameMode>>,
     mut current_player: ResMut<CurrentPlayer>,
+    mut record: ResMut<GameRecord>,
     mut slot_query: Query<&mut Slot>,
     mut slot_press_events: EventReader<SlotPressEvent>,
     mut move_events: EventWriter<MoveEvent>,
     mut capture_events: EventWriter<CaptureEvent>,
 ) {
     for event in slot_press_events.read() {
-        let mut counts: Vec<u32> = vec![0; Board::LENGTH];
+        let mut position = Position {
+            counts: [0; Board::LENGTH],
+            current: current_player.0,
+        };
 
         for slot in &mut slot_query.iter() {
-            counts[slot.index] = slot.count;
+            position.counts[slot.index] = slot.count;
         }
 
-        let start = slot_query.get(event.0).unwrap();
-        let mut index = start.index;
-
-        loop {
-            let mut stack = counts[index];
-            let mut moves: VecDeque<Entity> = VecDeque::new();
-            counts[index] = 0;
-
-            moves.push_back(board.slots[index]);
-
-            while stack > 0 {
-                index = (index + 1) % Board::LENGTH;
-
-                if index == Board::get_store(current_player.0.flip()) {
-                    // skip the opponent's store
-                    continue;
-                }
-
-                counts[index] += 1;
-                stack -= 1;
-
-                moves.push_back(board.slots[index]);
-            }
-
-            move_events.send(MoveEvent(moves));
-
-            let move_end_action = handle_move_end(
-                &board,
-                index,
-                *game_mode.get(),
-                current_player.0,
-                &mut counts,
-                &mut capture_events,
-            );
-
-            match move_end_action {
-                MoveEndAction::Repeat => continue,
-                MoveEndAction::Continue => break,
-                MoveEndAction::End => {
-                    current_player.flip();
-                    break;
-                }
-            }
-        }
+        let player = current_player.0;
+        let start = slot_query.get(event.0).unwrap().index;
+        let outcome = position.play(start, *game_mode.get());
 
-        for mut slot in &mut slot_query {
-            slot.count = counts[slot.index];
+        for lap in outcome.laps {
+            move_events.send(MoveEvent(
+                lap.into_iter().map(|index| board.slots[index]).collect(),
+            ));
         }
-    }
-}
-
-fn handle_move_end(
-    board: &Board,
-    index: usize,
-    game_mode: GameMode,
-    current_player: Player,
-    counts: &mut Vec<u32>,
-    capture_evw: &mut EventWriter<CaptureEvent>,
-) -> MoveEndAction {
-    if index == Board::get_store(current_player) {
-        return MoveEndAction::Continue;
-    }
-
-    match game_mode {
-        GameMode::Capture => {
-            if counts[index] == 1 && Board::owner(index) == current_player {
-                let opposite_index = Board::LENGTH - index - 2;
 
-                if counts[opposite_index] > 0 {
-                    let store = Board::get_store(current_player);
+        if let Some(capture) = outcome.capture {
+            capture_events.send(CaptureEvent {
+                slots: capture
+                    .pits
+                    .into_iter()
+                    .map(|index| board.slots[index])
+                    .collect(),
+                store: board.slots[capture.store],
+            });
+        }
 
-                    counts[store] += counts[opposite_index] + 1;
-                    counts[opposite_index] = 0;
-                    counts[index] = 0;
+        for mut slot in &mut slot_query {
+            slot.count = position.counts[slot.index];
+        }
 
-                    capture_evw.send(CaptureEvent {
-                        slots: vec![board.slots[index], board.slots[opposite_index]],
-                        store: board.slots[store],
-                    });
+        current_player.0 = position.current;
 
-                    return MoveEndAction::Continue;
-                }
-            }
-        }
-        GameMode::Avalanche => {
-            if counts[index] > 1 {
-                return MoveEndAction::Repeat;
-            }
-        }
+        record.moves.push(MoveRecord {
+            player,
+            index: start,
+            captured: outcome.captured,
+            extra_turn: outcome.extra_turn,
+        });
     }
-
-    MoveEndAction::End
 }
 
+#[allow(clippy::too_many_arguments)]
 fn check_game_over(
     mut winner: ResMut<Winner>,
+    mut record: ResMut<GameRecord>,
     mut game_state: ResMut<NextState<GameState>>,
-    capture_events: EventWriter<CaptureEvent>,
+    mut capture_events: EventWriter<CaptureEvent>,
     slot_query: Query<&Slot>,
     board: Res<Board>,
     game_mode: Res<State<GameMode>>,
+    current_player: Res<CurrentPlayer>,
 ) {
-    let slots = &board.slots.clone();
-
-    let mut scores = (0, 0);
-    let mut empty = (true, true);
-
-    for slot in slots {
-        if let Ok(slot) = slot_query.get(*slot) {
-            match Board::owner(slot.index) {
-                Player::One => {
-                    if Board::is_store(slot.index) {
-                        scores.0 = slot.count;
-                    } else if slot.count > 0 {
-                        empty.0 = false;
-                    }
-                }
-                Player::Two => {
-                    if Board::is_store(slot.index) {
-                        scores.1 = slot.count;
-                    } else if slot.count > 0 {
-                        empty.1 = false;
-                    }
-                }
-            }
-        }
+    let mut position = Position {
+        counts: [0; Board::LENGTH],
+        current: current_player.0,
+    };
+
+    for slot in &slot_query {
+        position.counts[slot.index] = slot.count;
     }
 
-    if !empty.0 && !empty.1 {
+    if !position.is_over() {
         return;
     }
 
-    if *game_mode.get() == GameMode::Capture {
-        if !empty.0 {
-            scores.0 += capture_side(capture_events, slot_query, Player::One, slots);
-        } else if !empty.1 {
-            scores.1 += capture_side(capture_events, slot_query, Player::Two, slots);
-        }
+    if let Some((player, seeds)) = position.sweep(*game_mode.get()) {
+        record.swept[player as usize] = seeds;
+
+        capture_events.send(CaptureEvent {
+            slots: board.slots[Board::get_slots(player)].to_vec(),
+            store: board.slots[Board::get_store(player)],
+        });
     }
 
-    winner.0 = match scores.0.cmp(&scores.1) {
-        Ordering::Greater => Some(Player::One),
-        Ordering::Less => Some(Player::Two),
-        Ordering::Equal => None,
-    };
+    winner.reason = EndReason::Score;
+    winner.player = position.leader();
 
     game_state.set(GameState::Over);
 }
 
-fn capture_side(
-    mut capture_events: EventWriter<CaptureEvent>,
-    slot_query: Query<&Slot>,
-    player: Player,
-    slots: &[Entity],
-) -> u32 {
-    let slot_slice = Board::get_slots(player);
-    let store_index = Board::get_store(player);
-
-    capture_events.send(CaptureEvent {
-        slots: slots[slot_slice.clone()].to_vec(),
-        store: slots[store_index],
-    });
-
-    slots[slot_slice].iter().fold(0, |acc, slot| {
-        let count = slot_query.get(*slot).unwrap().count;
-        acc + count
-    })
-}
-
 fn handle_animation_start(mut game_state: ResMut<NextState<GameState>>) {
     game_state.set(GameState::Playing);
 }
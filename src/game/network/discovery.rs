@@ -0,0 +1,360 @@
+use super::{protocol::PROTOCOL_VERSION, Lobby, Session, DEFAULT_PORT};
+use crate::{
+    game::Player,
+    menu::{MenuState, Selected},
+    profile::Profiles,
+    states::GameMode,
+};
+use bevy::prelude::*;
+use bevy_persistent::Persistent;
+use std::{
+    fmt,
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    str::FromStr,
+    time::Duration,
+};
+
+pub const DISCOVERY_PORT: u16 = 40405;
+const ADVERTISE_INTERVAL: f32 = 1.;
+/// Games that haven't been heard from for this long are dropped from the list.
+const EXPIRY: Duration = Duration::from_secs(3);
+
+pub struct DiscoveryPlugin;
+
+impl Plugin for DiscoveryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Advertiser>()
+            .init_resource::<Discovered>()
+            .add_systems(Update, advertise)
+            .add_systems(OnEnter(MenuState::Browse), start_scanning)
+            .add_systems(Update, scan.run_if(in_state(MenuState::Browse)))
+            .add_systems(OnExit(MenuState::Browse), stop_scanning);
+    }
+}
+
+/// What a host broadcasts about its game, once per `ADVERTISE_INTERVAL`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advert {
+    /// Picked at random when hosting starts, so the same game heard twice is listed once.
+    pub id: u32,
+    pub port: u16,
+    pub mode: GameMode,
+    pub open: bool,
+    pub name: String,
+}
+
+impl fmt::Display for Advert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MANCALA {PROTOCOL_VERSION} {} {} {} {} {}",
+            self.id,
+            self.port,
+            self.mode,
+            if self.open { "OPEN" } else { "FULL" },
+            self.name
+        )
+    }
+}
+
+impl FromStr for Advert {
+    type Err = ();
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut parts = line.trim().splitn(7, ' ');
+
+        if parts.next() != Some("MANCALA") {
+            return Err(());
+        }
+
+        // a host we couldn't talk to anyway isn't worth listing
+        if parts.next().and_then(|version| version.parse().ok()) != Some(PROTOCOL_VERSION) {
+            return Err(());
+        }
+
+        Ok(Self {
+            id: parts.next().ok_or(())?.parse().map_err(|_| ())?,
+            port: parts.next().ok_or(())?.parse().map_err(|_| ())?,
+            mode: parts.next().ok_or(())?.parse()?,
+            open: match parts.next().ok_or(())? {
+                "OPEN" => true,
+                "FULL" => false,
+                _ => return Err(()),
+            },
+            name: parts.next().ok_or(())?.to_string(),
+        })
+    }
+}
+
+#[derive(Resource)]
+struct Advertiser {
+    socket: Option<UdpSocket>,
+    timer: Timer,
+    id: u32,
+}
+
+impl Default for Advertiser {
+    fn default() -> Self {
+        Self {
+            socket: None,
+            timer: Timer::from_seconds(ADVERTISE_INTERVAL, TimerMode::Repeating),
+            id: rand::random(),
+        }
+    }
+}
+
+impl Advertiser {
+    fn send(&mut self, advert: &Advert) {
+        if self.socket.is_none() {
+            self.socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+                .and_then(|socket| socket.set_broadcast(true).map(|_| socket))
+                .map_err(|error| warn!("can't advertise: {error}"))
+                .ok();
+        }
+
+        let Some(socket) = &self.socket else {
+            return;
+        };
+
+        let line = advert.to_string();
+
+        // broadcasts don't always loop back, so tell this machine directly as well
+        for address in [Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST] {
+            if let Err(error) = socket.send_to(line.as_bytes(), (address, DISCOVERY_PORT)) {
+                debug!("failed to advertise to {address}: {error}");
+            }
+        }
+    }
+}
+
+/// A game heard from while browsing.
+#[derive(Debug, Clone)]
+pub struct DiscoveredGame {
+    pub address: SocketAddr,
+    pub advert: Advert,
+    last_seen: Duration,
+}
+
+/// Games found on the local network, in the order they were first heard from.
+#[derive(Resource, Default)]
+pub struct Discovered {
+    pub games: Vec<DiscoveredGame>,
+    /// Whether we're listening at all. Only one instance per machine can browse at a time.
+    pub listening: bool,
+    socket: Option<UdpSocket>,
+}
+
+fn advertise(
+    mut advertiser: ResMut<Advertiser>,
+    lobby: Res<Lobby>,
+    session: Option<Res<Session>>,
+    game_mode: Res<State<GameMode>>,
+    selected: Res<Selected>,
+    profiles: Res<Persistent<Profiles>>,
+    time: Res<Time>,
+) {
+    let advert = match (lobby.as_ref(), session) {
+        (Lobby::Hosting(listener), _) => Advert {
+            id: advertiser.id,
+            port: listener
+                .local_addr()
+                .map_or(DEFAULT_PORT, |address| address.port()),
+            mode: *game_mode.get(),
+            open: true,
+            name: profiles.0[selected.get(Player::One)].name.clone(),
+        },
+        (_, Some(session)) if session.is_host() => Advert {
+            id: advertiser.id,
+            port: DEFAULT_PORT,
+            mode: *game_mode.get(),
+            open: false,
+            name: session.names[0].clone(),
+        },
+        _ => {
+            // a new game is a new listing
+            if advertiser.socket.take().is_some() {
+                advertiser.id = rand::random();
+            }
+
+            return;
+        }
+    };
+
+    if advertiser.timer.tick(time.delta()).just_finished() || advertiser.socket.is_none() {
+        advertiser.send(&advert);
+    }
+}
+
+fn start_scanning(mut discovered: ResMut<Discovered>) {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))
+        .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+
+    match socket {
+        Ok(socket) => {
+            discovered.socket = Some(socket);
+            discovered.listening = true;
+        }
+        Err(error) => {
+            warn!("can't listen for games: {error}");
+            discovered.listening = false;
+        }
+    }
+
+    discovered.games.clear();
+}
+
+fn scan(mut discovered: ResMut<Discovered>, time: Res<Time>) {
+    let now = time.elapsed();
+    let mut changed = false;
+
+    // packets arrive every second, so only flag a change when the list itself changes
+    let list = discovered.bypass_change_detection();
+
+    if let Some(socket) = &list.socket {
+        let mut buffer = [0; 512];
+
+        loop {
+            let (read, source) = match socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    warn!("stopped listening for games: {error}");
+                    break;
+                }
+            };
+
+            let Ok(advert) = String::from_utf8_lossy(&buffer[..read]).parse::<Advert>() else {
+                continue;
+            };
+
+            let address = SocketAddr::new(source.ip(), advert.port);
+
+            match list
+                .games
+                .iter_mut()
+                .find(|game| game.advert.id == advert.id)
+            {
+                Some(game) => {
+                    changed |= game.advert != advert;
+                    game.advert = advert;
+                    game.last_seen = now;
+                }
+                None => {
+                    changed = true;
+                    list.games.push(DiscoveredGame {
+                        address,
+                        advert,
+                        last_seen: now,
+                    });
+                }
+            }
+        }
+    }
+
+    let count = list.games.len();
+
+    list.games
+        .retain(|game| now.saturating_sub(game.last_seen) < EXPIRY);
+
+    if changed || list.games.len() != count {
+        discovered.set_changed();
+    }
+}
+
+fn stop_scanning(mut discovered: ResMut<Discovered>) {
+    discovered.socket = None;
+    discovered.listening = false;
+    discovered.games.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advert() -> Advert {
+        Advert {
+            id: 3_141_592_653,
+            port: DEFAULT_PORT,
+            mode: GameMode::Avalanche,
+            open: true,
+            name: "ADA LOVELACE".to_string(),
+        }
+    }
+
+    #[test]
+    fn adverts_survive_the_round_trip() {
+        for mode in GameMode::iter() {
+            for open in [true, false] {
+                let advert = Advert {
+                    mode,
+                    open,
+                    ..advert()
+                };
+
+                assert_eq!(advert.to_string().parse(), Ok(advert));
+            }
+        }
+    }
+
+    #[test]
+    fn other_versions_are_ignored() {
+        let line = advert().to_string();
+
+        for version in [PROTOCOL_VERSION - 1, PROTOCOL_VERSION + 1] {
+            let other = line.replacen(
+                &format!("MANCALA {PROTOCOL_VERSION} "),
+                &format!("MANCALA {version} "),
+                1,
+            );
+
+            assert_eq!(other.parse::<Advert>(), Err(()), "{other}");
+        }
+    }
+
+    #[test]
+    fn truncated_adverts_are_ignored() {
+        let advert = advert();
+        let line = advert.to_string();
+
+        // the name runs to the end, so any cut that leaves some of it is still an advert
+        let name = line.len() - advert.name.len();
+
+        for end in 0..=name {
+            assert_eq!(line[..end].parse::<Advert>(), Err(()), "{:?}", &line[..end]);
+        }
+    }
+
+    #[test]
+    fn malformed_adverts_are_ignored() {
+        let line = advert().to_string();
+
+        for (from, to) in [
+            ("3141592653", "PI"),
+            ("3141592653", "-1"),
+            (&DEFAULT_PORT.to_string(), "70000"),
+            ("AVALANCHE", "OWARE"),
+            ("OPEN", "AJAR"),
+        ] {
+            let broken = line.replacen(from, to, 1);
+
+            assert_eq!(broken.parse::<Advert>(), Err(()), "{broken}");
+        }
+    }
+
+    #[test]
+    fn foreign_datagrams_are_ignored() {
+        for datagram in [
+            &b""[..],
+            b"\n",
+            b"HELLO 4 HOST ADA",
+            b"M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\r\n",
+            b"mancala 4 1 40404 CAPTURE OPEN ADA",
+            &[0xff, 0xfe, 0x00, 0x4d, 0x41],
+        ] {
+            let text = String::from_utf8_lossy(datagram);
+
+            assert_eq!(text.parse::<Advert>(), Err(()), "{text:?}");
+        }
+    }
+}
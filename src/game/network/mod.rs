@@ -0,0 +1,767 @@
+use self::protocol::{Connection, Message, PROTOCOL_VERSION};
+use super::{
+    ai::{AiPlayers, AiState},
+    animation::AnimationState,
+    board::SlotPressEvent,
+    clock::TimeControl,
+    helpers,
+    resign::ResignEvent,
+    Board, CurrentPlayer, EndReason, GameState, Player, Slot, StartingPlayer, Winner,
+};
+use crate::{
+    menu::{MenuState, Selected},
+    profile::Profiles,
+    states::{AppState, GameMode},
+    ui::UiAssets,
+};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, IoTaskPool, Task},
+};
+use bevy_persistent::Persistent;
+use std::{
+    collections::VecDeque,
+    io::{self, ErrorKind},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+pub mod discovery;
+pub mod protocol;
+mod sync;
+
+pub const DEFAULT_PORT: u16 = 40404;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Kept well under `RECONNECT_INTERVAL`, so one attempt is over before the next starts.
+const RECONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const RECONNECT_INTERVAL: f32 = 2.;
+/// How long a dropped player has to come back before the game is given up.
+const RECONNECT_GRACE: Duration = Duration::from_secs(60);
+/// How long the host keeps a connection that hasn't said who it is.
+const GREETING_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(discovery::DiscoveryPlugin)
+            .init_resource::<Lobby>()
+            .add_event::<LobbyEvent>()
+            .add_systems(
+                Update,
+                (handle_lobby_events, poll_lobby)
+                    .chain()
+                    .run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(
+                Update,
+                (
+                    receive,
+                    reconnect.run_if(not(is_host)),
+                    give_up,
+                    forward_moves.run_if(is_host),
+                    forward_resignations,
+                    apply_requests
+                        .run_if(is_host)
+                        .run_if(in_state(GameState::Idle))
+                        .run_if(in_state(AnimationState::Idle)),
+                    // only (re)join peers at a settled position, so the record matches the board
+                    sync::accept
+                        .run_if(is_host)
+                        .run_if(in_state(GameState::Idle))
+                        .run_if(in_state(AnimationState::Idle)),
+                    sync::apply
+                        .run_if(not(is_host))
+                        .run_if(not(in_state(GameState::Playing)))
+                        .run_if(in_state(AnimationState::Idle)),
+                    update_waiting_notice,
+                )
+                    .run_if(resource_exists::<Session>)
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(
+                OnEnter(GameState::Over),
+                forward_timeout.run_if(resource_exists::<Session>),
+            )
+            .add_systems(
+                OnExit(AppState::Game),
+                (end_session, helpers::despawn::<WaitingNotice>),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Host,
+    Client,
+    /// Watches a game without a seat, seeing every move the host sends.
+    Spectator,
+}
+
+/// Sent by the menu to open, join, watch or abandon a LAN game.
+#[derive(Event)]
+pub enum LobbyEvent {
+    Host,
+    Join(String),
+    Watch(String),
+    Cancel,
+}
+
+/// Where we are in setting up a networked game, before it starts.
+#[derive(Resource, Default)]
+pub enum Lobby {
+    #[default]
+    Idle,
+    Hosting(TcpListener),
+    /// Reaching the host in the background, then saying hello.
+    Connecting {
+        task: Task<io::Result<Connection>>,
+        role: Role,
+    },
+    /// Connected, waiting on `HELLO` as the host or `WELCOME` otherwise.
+    Handshake {
+        connection: Connection,
+        role: Role,
+        /// Kept by the host so spectators and dropped players can connect once the game is on.
+        listener: Option<TcpListener>,
+    },
+    Failed(String),
+}
+
+impl Lobby {
+    pub fn status(&self) -> String {
+        match self {
+            Self::Idle => String::new(),
+            Self::Hosting(listener) => listener.local_addr().map_or_else(
+                |_| "WAITING FOR PLAYER".to_string(),
+                |address| format!("WAITING ON PORT {}", address.port()),
+            ),
+            Self::Connecting { .. } | Self::Handshake { .. } => "CONNECTING".to_string(),
+            Self::Failed(reason) => reason.clone(),
+        }
+    }
+}
+
+/// A networked game in progress. Only present while playing over the network.
+#[derive(Resource)]
+pub struct Session {
+    pub role: Role,
+    /// The seat played on this machine, if any.
+    pub seat: Option<Player>,
+    pub names: [String; 2],
+    /// Set on the client between requesting a move and the host answering.
+    pub awaiting_reply: bool,
+    /// Set while the game is held for a dropped player to come back.
+    pub waiting: bool,
+    /// Our own name, sent again when reconnecting.
+    name: String,
+    /// Handed to the client by the host's `WELCOME`, and the only way back into its seat after
+    /// dropping. Picked at random by the host, so nobody else on the network can guess it.
+    token: Option<u64>,
+    /// The host's address, for clients and spectators to reconnect to.
+    address: Option<SocketAddr>,
+    /// The host for clients and spectators, the other player for the host.
+    connection: Option<Connection>,
+    /// A client's or spectator's attempt to get back to the host, while it's under way.
+    reconnecting: Option<Task<io::Result<Connection>>>,
+    spectators: Vec<Connection>,
+    /// Connections the host accepted that haven't said who they are yet, and when they connected.
+    pending: Vec<(Connection, Duration)>,
+    listener: Option<TcpListener>,
+    /// Moves requested by the client, waiting for the host to be idle.
+    requests: VecDeque<usize>,
+    /// When the connection dropped, while waiting for it to come back.
+    lost_at: Option<Duration>,
+    retry: Timer,
+    /// Messages that arrived along with `WELCOME`, before the session existed.
+    backlog: Vec<Message>,
+    /// The game so far and the position to continue from, as sent by the host.
+    resync: sync::Resync,
+}
+
+impl Session {
+    fn new(
+        role: Role,
+        seat: Option<Player>,
+        name: String,
+        token: Option<u64>,
+        connection: Connection,
+        listener: Option<TcpListener>,
+    ) -> Self {
+        Self {
+            role,
+            seat,
+            names: [name.clone(), String::new()],
+            awaiting_reply: false,
+            waiting: false,
+            name,
+            token,
+            address: connection.peer_addr().ok(),
+            connection: Some(connection),
+            reconnecting: None,
+            spectators: vec![],
+            pending: vec![],
+            listener,
+            requests: VecDeque::new(),
+            lost_at: None,
+            retry: Timer::from_seconds(RECONNECT_INTERVAL, TimerMode::Repeating),
+            backlog: vec![],
+            resync: default(),
+        }
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.role == Role::Host
+    }
+
+    fn send(&mut self, message: &Message) {
+        let Some(connection) = &mut self.connection else {
+            return;
+        };
+
+        if let Err(error) = connection.send(message) {
+            warn!("failed to send {message}: {error}");
+        }
+    }
+
+    /// Sends to every spectator, dropping any that have gone away.
+    fn broadcast(&mut self, message: &Message) {
+        self.spectators
+            .retain_mut(|spectator| spectator.send(message).is_ok());
+    }
+
+    /// Asks the host to play `index` for us. The move only happens once the host echoes it back.
+    pub fn request_move(&mut self, index: usize) {
+        self.awaiting_reply = true;
+        self.send(&Message::Move(index));
+    }
+}
+
+pub fn is_host(session: Option<Res<Session>>) -> bool {
+    session.is_some_and(|session| session.role == Role::Host)
+}
+
+/// Whether a networked game is held for a dropped player to come back.
+pub fn waiting(session: Option<Res<Session>>) -> bool {
+    session.is_some_and(|session| session.waiting)
+}
+
+#[derive(Component)]
+struct WaitingNotice;
+
+fn hello(role: Role, name: &str, token: Option<u64>) -> Message {
+    match role {
+        Role::Spectator => Message::Watch {
+            version: PROTOCOL_VERSION,
+            name: name.to_string(),
+        },
+        _ => Message::Hello {
+            version: PROTOCOL_VERSION,
+            token,
+            name: name.to_string(),
+        },
+    }
+}
+
+fn handle_lobby_events(
+    mut lobby_events: EventReader<LobbyEvent>,
+    mut lobby: ResMut<Lobby>,
+    selected: Res<Selected>,
+    profiles: Res<Persistent<Profiles>>,
+) {
+    let name = &profiles.0[selected.get(Player::One)].name;
+
+    for event in lobby_events.read() {
+        *lobby = match event {
+            LobbyEvent::Host => host(),
+            LobbyEvent::Join(address) => join(address, Role::Client, name),
+            LobbyEvent::Watch(address) => join(address, Role::Spectator, name),
+            LobbyEvent::Cancel => Lobby::Idle,
+        };
+    }
+}
+
+fn host() -> Lobby {
+    let listener = TcpListener::bind(("0.0.0.0", DEFAULT_PORT))
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener));
+
+    match listener {
+        Ok(listener) => Lobby::Hosting(listener),
+        Err(error) => Lobby::Failed(format!("CAN'T HOST: {}", error.kind())),
+    }
+}
+
+fn join(address: &str, role: Role, name: &str) -> Lobby {
+    let address = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{address}:{DEFAULT_PORT}")
+    };
+
+    Lobby::Connecting {
+        task: connect(address, CONNECT_TIMEOUT, hello(role, name, None)),
+        role,
+    }
+}
+
+/// Resolves `address`, connects and says hello on the IO pool, since any of it can block for
+/// up to `timeout`. An address that doesn't resolve fails with `ErrorKind::InvalidInput`.
+fn connect(
+    address: impl ToSocketAddrs + Send + 'static,
+    timeout: Duration,
+    hello: Message,
+) -> Task<io::Result<Connection>> {
+    IoTaskPool::get().spawn(async move {
+        let address = address
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or(ErrorKind::InvalidInput)?;
+
+        let mut connection = Connection::new(TcpStream::connect_timeout(&address, timeout)?)?;
+        connection.send(&hello)?;
+
+        Ok(connection)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn poll_lobby(
+    mut commands: Commands,
+    mut lobby: ResMut<Lobby>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut next_game_mode: ResMut<NextState<GameMode>>,
+    mut time_control: ResMut<TimeControl>,
+    mut ai_players: ResMut<AiPlayers>,
+    mut ai_state: ResMut<NextState<AiState>>,
+    mut starting_player: ResMut<StartingPlayer>,
+    game_mode: Res<State<GameMode>>,
+    selected: Res<Selected>,
+    profiles: Res<Persistent<Profiles>>,
+) {
+    let name = profiles.0[selected.get(Player::One)].name.clone();
+
+    // polling happens every frame, so only flag a change when the status actually moves on
+    let (peer, token, backlog) = match lobby.bypass_change_detection() {
+        Lobby::Hosting(listener) => {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return,
+                Err(error) => {
+                    *lobby = Lobby::Failed(format!("CAN'T HOST: {}", error.kind()));
+                    return;
+                }
+            };
+
+            let Lobby::Hosting(listener) = std::mem::take(lobby.as_mut()) else {
+                return;
+            };
+
+            *lobby = match Connection::new(stream) {
+                Ok(connection) => Lobby::Handshake {
+                    connection,
+                    role: Role::Host,
+                    listener: Some(listener),
+                },
+                Err(error) => Lobby::Failed(format!("CAN'T HOST: {}", error.kind())),
+            };
+
+            return;
+        }
+        Lobby::Connecting { task, role } => {
+            let role = *role;
+
+            let Some(connection) = block_on(future::poll_once(task)) else {
+                return;
+            };
+
+            *lobby = match connection {
+                Ok(connection) => Lobby::Handshake {
+                    connection,
+                    role,
+                    listener: None,
+                },
+                Err(error) if error.kind() == ErrorKind::InvalidInput => {
+                    Lobby::Failed("BAD ADDRESS".to_string())
+                }
+                Err(error) => Lobby::Failed(format!("CAN'T JOIN: {}", error.kind())),
+            };
+
+            return;
+        }
+        Lobby::Handshake {
+            connection, role, ..
+        } => {
+            let Ok(messages) = connection.poll() else {
+                *lobby = Lobby::Failed("CONNECTION LOST".to_string());
+                return;
+            };
+
+            let mut peer = None;
+            let mut token = None;
+            let mut messages = messages.into_iter();
+
+            for message in messages.by_ref() {
+                match (*role, message) {
+                    (Role::Host, Message::Hello { version, .. }) if version != PROTOCOL_VERSION => {
+                        *lobby = Lobby::Failed("VERSION MISMATCH".to_string());
+                        return;
+                    }
+                    (
+                        Role::Host,
+                        Message::Hello {
+                            name: peer_name, ..
+                        },
+                    ) => {
+                        let rejoin = rand::random();
+                        let welcome = Message::Welcome {
+                            mode: *game_mode.get(),
+                            time_control: *time_control,
+                            token: Some(rejoin),
+                            name: name.clone(),
+                        };
+
+                        if connection.send(&welcome).is_ok() {
+                            peer = Some(peer_name);
+                            token = Some(rejoin);
+                        }
+                    }
+                    (Role::Host, Message::Watch { .. }) => {
+                        // nothing to watch yet, so go back to waiting for a player
+                        let Lobby::Handshake { listener, .. } = std::mem::take(lobby.as_mut())
+                        else {
+                            return;
+                        };
+
+                        *lobby = listener.map_or(Lobby::Idle, Lobby::Hosting);
+                        return;
+                    }
+                    (
+                        Role::Client | Role::Spectator,
+                        Message::Welcome {
+                            mode,
+                            time_control: host_time_control,
+                            token: rejoin,
+                            name: peer_name,
+                        },
+                    ) => {
+                        next_game_mode.set(mode);
+                        *time_control = host_time_control;
+                        peer = Some(peer_name);
+                        token = rejoin;
+                        // anything after this is for the game, which doesn't exist yet
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            match peer {
+                Some(peer) => (peer, token, messages.collect()),
+                None => return,
+            }
+        }
+        _ => return,
+    };
+
+    let Lobby::Handshake {
+        connection,
+        role,
+        listener,
+    } = std::mem::take(lobby.as_mut())
+    else {
+        return;
+    };
+
+    let (seat, names) = match role {
+        Role::Host => (Some(Player::One), [name.clone(), peer]),
+        Role::Client => (Some(Player::Two), [peer, name.clone()]),
+        // corrected by the PLAYERS the host sends right after
+        Role::Spectator => (None, [peer, String::new()]),
+    };
+
+    let mut session = Session::new(role, seat, name, token, connection, listener);
+
+    session.names = names;
+    session.backlog = backlog;
+
+    commands.insert_resource(session);
+
+    // nobody on either machine is played by the CPU
+    *ai_players = AiPlayers::default();
+    ai_state.set(AiState::Inactive);
+
+    starting_player.0 = Player::One;
+    app_state.set(AppState::Game);
+    menu_state.set(MenuState::Start);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn receive(
+    mut session: ResMut<Session>,
+    mut slot_press_events: EventWriter<SlotPressEvent>,
+    mut resign_events: EventWriter<ResignEvent>,
+    mut winner: ResMut<Winner>,
+    mut game_state: ResMut<NextState<GameState>>,
+    state: Res<State<GameState>>,
+    board: Res<Board>,
+    time: Res<Time>,
+) {
+    let session = session.as_mut();
+
+    // spectators never send anything, so this only notices the ones that left
+    session
+        .spectators
+        .retain_mut(|spectator| spectator.poll().is_ok());
+
+    let mut messages = std::mem::take(&mut session.backlog);
+
+    if let Some(connection) = &mut session.connection {
+        match connection.poll() {
+            Ok(polled) => messages.extend(polled),
+            Err(error) => {
+                warn!("lost connection: {error}");
+
+                session.connection = None;
+
+                // leaving the game over screen is not forfeiting
+                if *state.get() != GameState::Over {
+                    session.waiting = true;
+                    session.lost_at = Some(time.elapsed());
+                    session.broadcast(&Message::Waiting(true));
+                }
+            }
+        }
+    }
+
+    for message in messages {
+        match (session.role, message) {
+            (Role::Host, Message::Move(index)) => {
+                session.requests.push_back(index);
+            }
+            (Role::Client | Role::Spectator, Message::Move(index)) if index < Board::LENGTH => {
+                session.awaiting_reply = false;
+                slot_press_events.send(SlotPressEvent(board.slots[index]));
+            }
+            (Role::Client, Message::Reject(index)) => {
+                warn!("host rejected move {index}");
+                session.awaiting_reply = false;
+            }
+            (Role::Client | Role::Spectator, Message::Timeout(player)) => {
+                winner.player = Some(player.flip());
+                winner.reason = EndReason::Timeout;
+                game_state.set(GameState::Over);
+            }
+            (Role::Spectator, Message::Left(player)) => {
+                winner.player = Some(player.flip());
+                winner.reason = EndReason::Disconnect;
+                game_state.set(GameState::Over);
+            }
+            (Role::Spectator, Message::Waiting(waiting)) if session.lost_at.is_none() => {
+                session.waiting = waiting;
+            }
+            (Role::Host, Message::Resign(_)) => {
+                // the client can only ever resign for itself
+                resign_events.send(ResignEvent(Player::Two));
+            }
+            (_, Message::Resign(player)) => {
+                resign_events.send(ResignEvent(player));
+            }
+            (Role::Client | Role::Spectator, message) => {
+                session.resync.receive(message);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Tries to get back to the host every `RECONNECT_INTERVAL` after losing it.
+fn reconnect(mut session: ResMut<Session>, state: Res<State<GameState>>, time: Res<Time>) {
+    if session.connection.is_some() || *state.get() == GameState::Over {
+        return;
+    }
+
+    if let Some(task) = &mut session.reconnecting {
+        let Some(connection) = block_on(future::poll_once(task)) else {
+            return;
+        };
+
+        session.reconnecting = None;
+
+        match connection {
+            // still waiting until the host's resync has been applied
+            Ok(connection) => session.connection = Some(connection),
+            Err(error) => debug!("reconnecting failed: {error}"),
+        }
+
+        return;
+    }
+
+    if !session.retry.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let Some(address) = session.address else {
+        return;
+    };
+
+    let hello = hello(session.role, &session.name, session.token);
+
+    session.reconnecting = Some(connect(address, RECONNECT_TIMEOUT, hello));
+}
+
+/// Ends the game once a dropped player has been gone for `RECONNECT_GRACE`.
+fn give_up(
+    mut session: ResMut<Session>,
+    mut winner: ResMut<Winner>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut app_state: ResMut<NextState<AppState>>,
+    time: Res<Time>,
+) {
+    let Some(lost_at) = session.lost_at else {
+        return;
+    };
+
+    if time.elapsed().saturating_sub(lost_at) < RECONNECT_GRACE {
+        return;
+    }
+
+    session.lost_at = None;
+    session.waiting = false;
+
+    match session.seat {
+        Some(seat) => {
+            if session.is_host() {
+                session.broadcast(&Message::Left(seat.flip()));
+            }
+
+            winner.player = Some(seat);
+            winner.reason = EndReason::Disconnect;
+            game_state.set(GameState::Over);
+        }
+        // a spectator has nothing left to watch
+        None => {
+            app_state.set(AppState::Menu);
+            game_state.set(GameState::None);
+        }
+    }
+}
+
+/// Validates one of the client's requests against the host's board, playing it if it's legal.
+fn apply_requests(
+    mut session: ResMut<Session>,
+    mut slot_press_events: EventWriter<SlotPressEvent>,
+    current_player: Res<CurrentPlayer>,
+    board: Res<Board>,
+    slot_query: Query<&Slot>,
+) {
+    if session.waiting {
+        return;
+    }
+
+    let Some(index) = session.requests.pop_front() else {
+        return;
+    };
+
+    let peer = Player::Two;
+
+    let legal = current_player.0 == peer
+        && Board::get_slots(peer).contains(&index)
+        && slot_query
+            .get(board.slots[index])
+            .is_ok_and(|slot| slot.count > 0);
+
+    if legal {
+        // echoed to the client by forward_moves, along with every other move
+        slot_press_events.send(SlotPressEvent(board.slots[index]));
+    } else {
+        session.send(&Message::Reject(index));
+    }
+}
+
+fn forward_moves(
+    mut session: ResMut<Session>,
+    mut slot_press_events: EventReader<SlotPressEvent>,
+    slot_query: Query<&Slot>,
+) {
+    for SlotPressEvent(slot) in slot_press_events.read() {
+        if let Ok(slot) = slot_query.get(*slot) {
+            let message = Message::Move(slot.index);
+
+            session.send(&message);
+            session.broadcast(&message);
+        }
+    }
+}
+
+fn forward_resignations(mut session: ResMut<Session>, mut resign_events: EventReader<ResignEvent>) {
+    for ResignEvent(player) in resign_events.read() {
+        let message = Message::Resign(*player);
+
+        // the other player already knows when they're the one resigning
+        if session.seat == Some(*player) {
+            session.send(&message);
+        }
+
+        if session.is_host() {
+            session.broadcast(&message);
+        }
+    }
+}
+
+fn forward_timeout(mut session: ResMut<Session>, winner: Res<Winner>) {
+    if !session.is_host() || winner.reason != EndReason::Timeout {
+        return;
+    }
+
+    if let Some(player) = winner.player {
+        let message = Message::Timeout(player.flip());
+
+        session.send(&message);
+        session.broadcast(&message);
+    }
+}
+
+fn update_waiting_notice(
+    mut commands: Commands,
+    notice_query: Query<Entity, With<WaitingNotice>>,
+    session: Res<Session>,
+    ui_assets: Res<UiAssets>,
+) {
+    if notice_query.is_empty() != session.waiting {
+        return;
+    }
+
+    if !session.waiting {
+        for entity in &notice_query {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        return;
+    }
+
+    let value = match (session.role, session.lost_at) {
+        (Role::Host, _) => format!("WAITING FOR {}", session.names[1]),
+        (_, Some(_)) => "RECONNECTING".to_string(),
+        _ => "WAITING FOR PLAYER".to_string(),
+    };
+
+    let screen = helpers::get_screen(&mut commands);
+    let text = helpers::get_text(&mut commands, &ui_assets, &value);
+
+    commands
+        .entity(screen)
+        .insert((
+            WaitingNotice,
+            BackgroundColor(Color::rgba(0., 0., 0., 0.7)),
+            ZIndex::Global(1),
+        ))
+        .add_child(text);
+}
+
+fn end_session(mut commands: Commands, mut lobby: ResMut<Lobby>) {
+    // dropping the session closes every connection, which peers see as us leaving
+    commands.remove_resource::<Session>();
+    *lobby = Lobby::Idle;
+}
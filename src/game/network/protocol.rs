@@ -0,0 +1,571 @@
+use crate::{
+    game::{clock::TimeControl, MoveRecord, Player},
+    states::GameMode,
+};
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream},
+    str::FromStr,
+    time::Duration,
+};
+
+/// Bumped whenever a message changes shape, so mismatched builds refuse each other.
+pub const PROTOCOL_VERSION: u32 = 4;
+
+/// Far more than the longest sync of a finished game, so a peer sending more is misbehaving.
+const MAX_LINE: usize = 64 * 1024;
+/// How far a peer can fall behind reading before it's given up on.
+const MAX_QUEUED: usize = 4 * MAX_LINE;
+
+/// Everything sent over the wire, one message per line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Client to host, right after connecting or reconnecting. Reconnecting, it carries the token
+    /// the host's `WELCOME` gave out, which is all that gets the seat back.
+    Hello {
+        version: u32,
+        token: Option<u64>,
+        name: String,
+    },
+    /// Spectator to host, right after connecting.
+    Watch {
+        version: u32,
+        name: String,
+    },
+    /// Host to client, accepting the connection and fixing the rules for the game. Players get the
+    /// session's rejoin token, spectators don't.
+    Welcome {
+        mode: GameMode,
+        time_control: TimeControl,
+        token: Option<u64>,
+        name: String,
+    },
+    /// A pit index. From the client it's a request, from the host it's been applied.
+    Move(usize),
+    /// Host to client, refusing a requested move.
+    Reject(usize),
+    Resign(Player),
+    /// Host to client, the given player ran out of time.
+    Timeout(Player),
+    /// Host to spectators, the given player never came back.
+    Left(Player),
+    /// Host to spectators, whether the game is held for a dropped player.
+    Waiting(bool),
+    /// Host to a (re)joining peer, both players' names.
+    Players([String; 2]),
+    /// Host to a (re)joining peer, one move of the game so far and the stores after it.
+    Record {
+        record: MoveRecord,
+        stores: [u32; 2],
+    },
+    /// Host to a (re)joining peer, the position to continue from. Follows every `Record`.
+    Sync {
+        current: Player,
+        clock: [Duration; 2],
+        counts: Vec<u32>,
+    },
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Hello {
+                version,
+                token,
+                name,
+            } => write!(f, "HELLO {version} {} {name}", encode_token(*token)),
+            Self::Watch { version, name } => write!(f, "WATCH {version} {name}"),
+            Self::Welcome {
+                mode,
+                time_control,
+                token,
+                name,
+            } => write!(
+                f,
+                "WELCOME {mode} {} {} {name}",
+                encode_time_control(time_control),
+                encode_token(*token)
+            ),
+            Self::Move(index) => write!(f, "MOVE {index}"),
+            Self::Reject(index) => write!(f, "REJECT {index}"),
+            Self::Resign(player) => write!(f, "RESIGN {}", encode_player(*player)),
+            Self::Timeout(player) => write!(f, "TIMEOUT {}", encode_player(*player)),
+            Self::Left(player) => write!(f, "LEFT {}", encode_player(*player)),
+            Self::Waiting(waiting) => write!(f, "WAITING {}", u8::from(*waiting)),
+            // names may contain spaces, so they're split on a tab instead
+            Self::Players([one, two]) => write!(f, "PLAYERS {one}\t{two}"),
+            Self::Record { record, stores } => write!(
+                f,
+                "RECORD {} {} {} {} {} {}",
+                encode_player(record.player),
+                record.index,
+                record.captured,
+                u8::from(record.extra_turn),
+                stores[0],
+                stores[1]
+            ),
+            Self::Sync {
+                current,
+                clock,
+                counts,
+            } => {
+                write!(
+                    f,
+                    "SYNC {} {} {}",
+                    encode_player(*current),
+                    clock[0].as_millis(),
+                    clock[1].as_millis()
+                )?;
+
+                for count in counts {
+                    write!(f, " {count}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Message {
+    type Err = ();
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut parts = line.trim_end_matches(['\r', '\n']).splitn(2, ' ');
+        let command = parts.next().ok_or(())?;
+        let rest = parts.next().unwrap_or("");
+
+        match command {
+            "HELLO" => {
+                let mut parts = rest.splitn(3, ' ');
+
+                Ok(Self::Hello {
+                    version: parts.next().ok_or(())?.parse().map_err(|_| ())?,
+                    token: decode_token(parts.next().ok_or(())?)?,
+                    name: parts.next().ok_or(())?.to_string(),
+                })
+            }
+            "WATCH" => {
+                let (version, name) = rest.split_once(' ').ok_or(())?;
+
+                Ok(Self::Watch {
+                    version: version.parse().map_err(|_| ())?,
+                    name: name.to_string(),
+                })
+            }
+            "WELCOME" => {
+                let mut parts = rest.splitn(4, ' ');
+
+                Ok(Self::Welcome {
+                    mode: parts.next().ok_or(())?.parse()?,
+                    time_control: decode_time_control(parts.next().ok_or(())?)?,
+                    token: decode_token(parts.next().ok_or(())?)?,
+                    name: parts.next().ok_or(())?.to_string(),
+                })
+            }
+            "MOVE" => Ok(Self::Move(rest.parse().map_err(|_| ())?)),
+            "REJECT" => Ok(Self::Reject(rest.parse().map_err(|_| ())?)),
+            "RESIGN" => Ok(Self::Resign(decode_player(rest)?)),
+            "TIMEOUT" => Ok(Self::Timeout(decode_player(rest)?)),
+            "LEFT" => Ok(Self::Left(decode_player(rest)?)),
+            "WAITING" => Ok(Self::Waiting(decode_flag(rest)?)),
+            "PLAYERS" => {
+                let (one, two) = rest.split_once('\t').ok_or(())?;
+
+                Ok(Self::Players([one.to_string(), two.to_string()]))
+            }
+            "RECORD" => {
+                let parts: Vec<&str> = rest.split(' ').collect();
+                let [player, index, captured, extra_turn, one, two] = parts[..] else {
+                    return Err(());
+                };
+
+                Ok(Self::Record {
+                    record: MoveRecord {
+                        player: decode_player(player)?,
+                        index: index.parse().map_err(|_| ())?,
+                        captured: captured.parse().map_err(|_| ())?,
+                        extra_turn: decode_flag(extra_turn)?,
+                    },
+                    stores: [one.parse().map_err(|_| ())?, two.parse().map_err(|_| ())?],
+                })
+            }
+            "SYNC" => {
+                let mut parts = rest.split(' ');
+                let current = decode_player(parts.next().ok_or(())?)?;
+                let mut millis = || -> Result<Duration, ()> {
+                    let value = parts.next().ok_or(())?.parse().map_err(|_| ())?;
+                    Ok(Duration::from_millis(value))
+                };
+                let clock = [millis()?, millis()?];
+
+                Ok(Self::Sync {
+                    current,
+                    clock,
+                    counts: parts
+                        .map(|count| count.parse().map_err(|_| ()))
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+fn encode_player(player: Player) -> usize {
+    player as usize + 1
+}
+
+fn decode_player(token: &str) -> Result<Player, ()> {
+    match token {
+        "1" => Ok(Player::One),
+        "2" => Ok(Player::Two),
+        _ => Err(()),
+    }
+}
+
+fn decode_flag(token: &str) -> Result<bool, ()> {
+    match token {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(()),
+    }
+}
+
+fn encode_token(token: Option<u64>) -> String {
+    token.map_or("-".to_string(), |token| format!("{token:016x}"))
+}
+
+fn decode_token(token: &str) -> Result<Option<u64>, ()> {
+    match token {
+        "-" => Ok(None),
+        _ => u64::from_str_radix(token, 16).map(Some).map_err(|_| ()),
+    }
+}
+
+fn encode_time_control(time_control: &TimeControl) -> String {
+    match time_control {
+        TimeControl::Untimed => "none".to_string(),
+        TimeControl::SuddenDeath { total } => format!("sd{}", total.as_secs()),
+        TimeControl::Fischer { total, increment } => {
+            format!("f{}+{}", total.as_secs(), increment.as_secs())
+        }
+        TimeControl::PerMove { limit } => format!("pm{}", limit.as_secs()),
+    }
+}
+
+fn decode_time_control(token: &str) -> Result<TimeControl, ()> {
+    let seconds = |value: &str| value.parse().map(Duration::from_secs).map_err(|_| ());
+
+    if token == "none" {
+        Ok(TimeControl::Untimed)
+    } else if let Some(total) = token.strip_prefix("sd") {
+        Ok(TimeControl::SuddenDeath {
+            total: seconds(total)?,
+        })
+    } else if let Some(rest) = token.strip_prefix('f') {
+        let (total, increment) = rest.split_once('+').ok_or(())?;
+
+        Ok(TimeControl::Fischer {
+            total: seconds(total)?,
+            increment: seconds(increment)?,
+        })
+    } else if let Some(limit) = token.strip_prefix("pm") {
+        Ok(TimeControl::PerMove {
+            limit: seconds(limit)?,
+        })
+    } else {
+        Err(())
+    }
+}
+
+/// A non-blocking, line-buffered TCP stream that can be polled once per frame.
+pub struct Connection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    /// Lines sent but not yet taken by the socket.
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            stream,
+            buffer: vec![],
+            outgoing: vec![],
+        })
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Queues `message`, writing as much as the socket takes right away and the rest on `poll`, so
+    /// a peer that stops reading can't hold up the game.
+    pub fn send(&mut self, message: &Message) -> io::Result<()> {
+        writeln!(self.outgoing, "{message}")?;
+
+        self.flush()
+    }
+
+    /// Writes what's queued until the socket would block. Errors once the peer is gone, or once it
+    /// has fallen `MAX_QUEUED` behind.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        if self.outgoing.len() > MAX_QUEUED {
+            return Err(ErrorKind::TimedOut.into());
+        }
+
+        Ok(())
+    }
+
+    /// Sends whatever is still queued and reads whatever has arrived. Lines that don't parse are
+    /// dropped. Errors once the peer is gone, once a line grows past `MAX_LINE` without ending, or
+    /// once the peer stops reading.
+    pub fn poll(&mut self) -> io::Result<Vec<Message>> {
+        self.flush()?;
+
+        let mut chunk = [0; 512];
+
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(read) => {
+                    self.buffer.extend_from_slice(&chunk[..read]);
+
+                    let unterminated = self
+                        .buffer
+                        .iter()
+                        .rev()
+                        .take_while(|&&byte| byte != b'\n')
+                        .count();
+
+                    if unterminated > MAX_LINE {
+                        return Err(ErrorKind::InvalidData.into());
+                    }
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        let mut messages = vec![];
+
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+
+            if let Ok(message) = String::from_utf8_lossy(&line).parse() {
+                messages.push(message);
+            }
+        }
+
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::TcpListener, thread, time::Instant};
+
+    /// Long enough for anything sent over localhost to arrive.
+    const DEADLINE: Duration = Duration::from_secs(5);
+
+    fn every_message() -> Vec<Message> {
+        vec![
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                token: None,
+                name: "PL 2".to_string(),
+            },
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                token: Some(u64::MAX),
+                name: "PL2".to_string(),
+            },
+            Message::Watch {
+                version: PROTOCOL_VERSION,
+                name: "SOMEONE ELSE".to_string(),
+            },
+            Message::Welcome {
+                mode: GameMode::Avalanche,
+                time_control: TimeControl::Untimed,
+                token: Some(0x1234),
+                name: "PL 1".to_string(),
+            },
+            Message::Welcome {
+                mode: GameMode::Capture,
+                time_control: TimeControl::Fischer {
+                    total: Duration::from_secs(300),
+                    increment: Duration::from_secs(5),
+                },
+                token: None,
+                name: "PL1".to_string(),
+            },
+            Message::Welcome {
+                mode: GameMode::Capture,
+                time_control: TimeControl::SuddenDeath {
+                    total: Duration::from_secs(600),
+                },
+                token: None,
+                name: "PL1".to_string(),
+            },
+            Message::Welcome {
+                mode: GameMode::Capture,
+                time_control: TimeControl::PerMove {
+                    limit: Duration::from_secs(10),
+                },
+                token: None,
+                name: "PL1".to_string(),
+            },
+            Message::Move(12),
+            Message::Reject(3),
+            Message::Resign(Player::One),
+            Message::Timeout(Player::Two),
+            Message::Left(Player::Two),
+            Message::Waiting(true),
+            Message::Waiting(false),
+            Message::Players(["PL 1".to_string(), "PL 2".to_string()]),
+            Message::Record {
+                record: MoveRecord {
+                    player: Player::Two,
+                    index: 9,
+                    captured: 4,
+                    extra_turn: true,
+                },
+                stores: [10, 14],
+            },
+            Message::Sync {
+                current: Player::One,
+                clock: [Duration::from_millis(1500), Duration::from_millis(0)],
+                counts: vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13],
+            },
+        ]
+    }
+
+    /// Polls `connection` until it has received a message.
+    fn receive(connection: &mut Connection) -> Message {
+        let start = Instant::now();
+
+        while start.elapsed() < DEADLINE {
+            let messages = connection.poll().expect("the connection dropped");
+
+            if let Some(message) = messages.into_iter().next() {
+                return message;
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        panic!("nothing arrived within {DEADLINE:?}");
+    }
+
+    #[test]
+    fn messages_survive_formatting() {
+        for message in every_message() {
+            let line = message.to_string();
+
+            assert_eq!(line.parse::<Message>(), Ok(message), "{line}");
+        }
+    }
+
+    #[test]
+    fn malformed_lines_are_refused() {
+        for line in [
+            "",
+            "HELLO",
+            "HELLO 4",
+            "HELLO x - PL1",
+            "HELLO 4 nothex PL1",
+            "WELCOME AVALANCHE none",
+            "WELCOME CHESS none - PL1",
+            "WELCOME AVALANCHE sd - PL1",
+            "MOVE",
+            "MOVE -1",
+            "RESIGN 3",
+            "WAITING 2",
+            "PLAYERS PL1 PL2",
+            "RECORD 1 2 3",
+            "SYNC 1 100",
+            "SYNC 1 100 100 1 x",
+            "GOODBYE",
+        ] {
+            assert!(line.parse::<Message>().is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn a_peer_that_stops_reading_is_given_up_on() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let mut host =
+            Connection::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap()).unwrap();
+        let _client = listener.accept().unwrap();
+
+        let long = Message::Players(["X".repeat(MAX_LINE / 2), String::new()]);
+        let start = Instant::now();
+
+        // the socket's buffers fill up first, then the queue does, without ever blocking
+        while host.send(&long).is_ok() {
+            assert!(start.elapsed() < DEADLINE, "sending never failed");
+        }
+    }
+
+    #[test]
+    fn peers_shake_hands_over_localhost() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let mut client = Connection::new(TcpStream::connect(address).unwrap()).unwrap();
+        let mut host = Connection::new(listener.accept().unwrap().0).unwrap();
+
+        let hello = Message::Hello {
+            version: PROTOCOL_VERSION,
+            token: None,
+            name: "PL2".to_string(),
+        };
+        let welcome = Message::Welcome {
+            mode: GameMode::Capture,
+            time_control: TimeControl::Untimed,
+            token: Some(42),
+            name: "PL1".to_string(),
+        };
+
+        client.send(&hello).unwrap();
+        assert_eq!(receive(&mut host), hello);
+
+        host.send(&welcome).unwrap();
+        assert_eq!(receive(&mut client), welcome);
+
+        client.send(&Message::Move(2)).unwrap();
+        assert_eq!(receive(&mut host), Message::Move(2));
+
+        drop(client);
+
+        let start = Instant::now();
+
+        // the host notices the client has gone
+        while host.poll().is_ok() {
+            assert!(
+                start.elapsed() < DEADLINE,
+                "the dropped client went unnoticed"
+            );
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use super::{
+    protocol::{Connection, Message, PROTOCOL_VERSION},
+    Session, GREETING_TIMEOUT,
+};
+use crate::{
+    game::{
+        chart::ScoreHistory,
+        clock::{Clock, TimeControl},
+        marble::{self, MarbleEvent, MarbleStack},
+        turn_indicator::TurnIndicatorLabel,
+        Board, CurrentPlayer, GameRecord, MoveRecord, Player, Slot,
+    },
+    states::GameMode,
+};
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// The game so far as sent by the host, collected until the `SYNC` that completes it.
+#[derive(Default)]
+pub struct Resync {
+    names: Option<[String; 2]>,
+    records: Vec<(MoveRecord, [u32; 2])>,
+    ready: Option<Position>,
+}
+
+struct Position {
+    names: Option<[String; 2]>,
+    records: Vec<(MoveRecord, [u32; 2])>,
+    current: Player,
+    clock: [Duration; 2],
+    counts: Vec<u32>,
+}
+
+impl Resync {
+    pub fn receive(&mut self, message: Message) {
+        match message {
+            // the host starts every resync with a fresh welcome
+            Message::Welcome { .. } => *self = default(),
+            Message::Players(names) => self.names = Some(names),
+            Message::Record { record, stores } => self.records.push((record, stores)),
+            Message::Sync {
+                current,
+                clock,
+                counts,
+            } if counts.len() == Board::LENGTH => {
+                self.ready = Some(Position {
+                    names: self.names.take(),
+                    records: std::mem::take(&mut self.records),
+                    current,
+                    clock,
+                    counts,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Everything a peer needs to pick the game up from where it is now. Only a returning player is
+/// given the rejoin `token` again.
+#[allow(clippy::too_many_arguments)]
+fn snapshot(
+    session: &Session,
+    token: Option<u64>,
+    game_mode: GameMode,
+    time_control: TimeControl,
+    current_player: Player,
+    clock: &Clock,
+    record: &GameRecord,
+    history: &ScoreHistory,
+    counts: Vec<u32>,
+) -> Vec<Message> {
+    let mut messages = vec![
+        Message::Welcome {
+            mode: game_mode,
+            time_control,
+            token,
+            name: session.names[0].clone(),
+        },
+        Message::Players(session.names.clone()),
+    ];
+
+    // the first snapshot is the empty board before any move
+    for (record, snapshot) in record.moves.iter().zip(history.0.iter().skip(1)) {
+        messages.push(Message::Record {
+            record: *record,
+            stores: snapshot.stores,
+        });
+    }
+
+    messages.push(Message::Sync {
+        current: current_player,
+        clock: clock.remaining,
+        counts,
+    });
+
+    messages
+}
+
+/// Takes in new connections on the host, seating a returning player or adding a spectator.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn accept(
+    mut session: ResMut<Session>,
+    game_mode: Res<State<GameMode>>,
+    time_control: Res<TimeControl>,
+    current_player: Res<CurrentPlayer>,
+    clock: Res<Clock>,
+    record: Res<GameRecord>,
+    history: Res<ScoreHistory>,
+    board: Res<Board>,
+    slot_query: Query<&Slot>,
+    time: Res<Time>,
+) {
+    let session = session.as_mut();
+
+    if let Some(listener) = &session.listener {
+        while let Ok((stream, _)) = listener.accept() {
+            match Connection::new(stream) {
+                Ok(connection) => session.pending.push((connection, time.elapsed())),
+                Err(error) => warn!("failed to accept connection: {error}"),
+            }
+        }
+    }
+
+    for (mut connection, connected_at) in std::mem::take(&mut session.pending) {
+        // anything that isn't a greeting is dropped, closing the connection
+        let Ok(messages) = connection.poll() else {
+            continue;
+        };
+
+        let Some(greeting) = messages.into_iter().next() else {
+            // and so is a connection that keeps quiet
+            if time.elapsed().saturating_sub(connected_at) < GREETING_TIMEOUT {
+                session.pending.push((connection, connected_at));
+            }
+
+            continue;
+        };
+
+        let returning = match &greeting {
+            Message::Hello { version, token, .. } if *version == PROTOCOL_VERSION => {
+                // only the player that dropped can take their seat back, whatever name is given
+                if session.connection.is_some() || token.is_none() || *token != session.token {
+                    continue;
+                }
+
+                true
+            }
+            Message::Watch { version, .. } if *version == PROTOCOL_VERSION => false,
+            _ => continue,
+        };
+
+        let counts = board
+            .slots
+            .iter()
+            .map(|slot| slot_query.get(*slot).map_or(0, |slot| slot.count))
+            .collect();
+
+        let messages = snapshot(
+            session,
+            session.token.filter(|_| returning),
+            *game_mode.get(),
+            *time_control,
+            current_player.0,
+            &clock,
+            &record,
+            &history,
+            counts,
+        );
+
+        if messages
+            .iter()
+            .any(|message| connection.send(message).is_err())
+        {
+            continue;
+        }
+
+        if returning {
+            session.connection = Some(connection);
+            session.waiting = false;
+            session.lost_at = None;
+            session.broadcast(&Message::Waiting(false));
+        } else {
+            session.spectators.push(connection);
+        }
+    }
+}
+
+/// Puts the board, record and clocks where the host says they are.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn apply(
+    mut session: ResMut<Session>,
+    mut slot_query: Query<(Entity, &mut Slot)>,
+    mut label_query: Query<&mut TurnIndicatorLabel>,
+    mut marble_events: EventWriter<MarbleEvent>,
+    mut current_player: ResMut<CurrentPlayer>,
+    mut clock: ResMut<Clock>,
+    mut record: ResMut<GameRecord>,
+    mut history: ResMut<ScoreHistory>,
+    stack_query: Query<&MarbleStack>,
+) {
+    let Some(position) = &session.resync.ready else {
+        return;
+    };
+
+    if !marble::restack(
+        &position.counts,
+        &mut slot_query,
+        &stack_query,
+        &mut marble_events,
+    ) {
+        return;
+    }
+
+    let Some(position) = session.resync.ready.take() else {
+        return;
+    };
+
+    if let Some(names) = position.names {
+        for mut label in &mut label_query {
+            label.name = names[label.player as usize].clone();
+        }
+
+        session.names = names;
+    }
+
+    current_player.0 = position.current;
+    clock.remaining = position.clock;
+    *record = GameRecord::from_records(&position.records);
+    *history = ScoreHistory::from_records(&position.records);
+
+    session.waiting = false;
+    session.lost_at = None;
+    session.awaiting_reply = false;
+}
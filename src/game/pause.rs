@@ -0,0 +1,255 @@
+use super::{
+    ai::AiPlayers,
+    clock::TimeControl,
+    correspondence::Correspondence,
+    helpers,
+    network::Session,
+    resign::{pending_offer, DrawOfferEvent, ResignEvent},
+    CurrentPlayer, GameState, Player,
+};
+use crate::{states::AppState, ui::UiAssets};
+use bevy::prelude::*;
+use std::fmt;
+
+const PAUSE_ALPHA: f32 = 0.7;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<PauseState>()
+            .add_systems(
+                Update,
+                (
+                    toggle
+                        .run_if(not(in_state(GameState::Over)))
+                        .run_if(not(pending_offer)),
+                    button_action.run_if(in_state(PauseState::Paused)),
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
+            .add_systems(OnEnter(PauseState::Paused), setup)
+            .add_systems(OnExit(PauseState::Paused), helpers::despawn::<PauseScreen>)
+            .add_systems(OnExit(AppState::Game), reset);
+    }
+}
+
+#[derive(Component)]
+struct PauseScreen;
+
+#[derive(Component, Clone, Copy)]
+enum PauseButtonAction {
+    Resume,
+    Restart,
+    Resign,
+    OfferDraw,
+    Quit,
+}
+
+impl fmt::Display for PauseButtonAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Resume => write!(f, "RESUME"),
+            Self::Restart => write!(f, "RESTART"),
+            Self::Resign => write!(f, "RESIGN"),
+            Self::OfferDraw => write!(f, "OFFER DRAW"),
+            Self::Quit => write!(f, "QUIT TO MENU"),
+        }
+    }
+}
+
+#[derive(States, SystemSet, Debug, Hash, PartialEq, Eq, Clone, Default)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Whether the game stands still. A LAN game can't be stopped from one side, so pausing there
+/// only covers the board and the clocks keep running.
+pub fn halted(pause_state: Res<State<PauseState>>, session: Option<Res<Session>>) -> bool {
+    *pause_state.get() == PauseState::Paused && session.is_none()
+}
+
+fn toggle(
+    keys: Res<ButtonInput<KeyCode>>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    match pause_state.get() {
+        PauseState::Running => next_pause_state.set(PauseState::Paused),
+        PauseState::Paused => next_pause_state.set(PauseState::Running),
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    ui_assets: Res<UiAssets>,
+    session: Option<Res<Session>>,
+    correspondence: Option<Res<Correspondence>>,
+    ai_players: Res<AiPlayers>,
+    time_control: Res<TimeControl>,
+) {
+    let screen = helpers::get_screen(&mut commands);
+
+    commands.entity(screen).insert((
+        PauseScreen,
+        BackgroundColor(Color::rgba(0.0, 0.0, 0.0, PAUSE_ALPHA)),
+        // the overlay should sit above the board, labels and turn indicators
+        ZIndex::Global(1),
+    ));
+
+    commands.entity(screen).with_children(|parent| {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(20.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    // only a LAN game's clock keeps going, and only if there is one
+                    if session.is_some() && *time_control != TimeControl::Untimed {
+                        "PAUSED, THE CLOCK IS RUNNING"
+                    } else {
+                        "PAUSED"
+                    },
+                    TextStyle {
+                        font: ui_assets.font.clone(),
+                        font_size: 40.0,
+                        color: Color::WHITE,
+                    },
+                ));
+
+                let actions = match session.map(|session| session.seat) {
+                    // spectators can only leave, and a correspondence game just waits for the next file
+                    Some(None) => vec![PauseButtonAction::Resume, PauseButtonAction::Quit],
+                    None if correspondence.is_some() => {
+                        vec![PauseButtonAction::Resume, PauseButtonAction::Quit]
+                    }
+                    // nobody is playing a game between two CPUs, so there's nobody to resign
+                    None if ai_players.is_spectating() => vec![
+                        PauseButtonAction::Resume,
+                        PauseButtonAction::Restart,
+                        PauseButtonAction::Quit,
+                    ],
+                    // neither side can restart a shared game, and draws aren't negotiated over the wire
+                    Some(Some(_)) => vec![
+                        PauseButtonAction::Resume,
+                        PauseButtonAction::Resign,
+                        PauseButtonAction::Quit,
+                    ],
+                    None => vec![
+                        PauseButtonAction::Resume,
+                        PauseButtonAction::Restart,
+                        PauseButtonAction::Resign,
+                        PauseButtonAction::OfferDraw,
+                        PauseButtonAction::Quit,
+                    ],
+                };
+
+                for action in actions {
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                background_color: Color::NONE.into(),
+                                ..default()
+                            },
+                            action,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                action.to_string(),
+                                TextStyle {
+                                    font: ui_assets.font.clone(),
+                                    font_size: 40.0,
+                                    color: Color::WHITE,
+                                },
+                            ));
+                        });
+                }
+            });
+    });
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn button_action(
+    interaction_query: Query<
+        (&Children, &Interaction, &PauseButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut pause_state: ResMut<NextState<PauseState>>,
+    mut resign_events: EventWriter<ResignEvent>,
+    mut draw_offer_events: EventWriter<DrawOfferEvent>,
+    current_player: Res<CurrentPlayer>,
+    ai_players: Res<AiPlayers>,
+    session: Option<Res<Session>>,
+) {
+    let acting_player = session.map_or_else(
+        || acting_player(&current_player, &ai_players),
+        |session| session.seat.unwrap_or(current_player.0),
+    );
+
+    for (children, interaction, action) in &interaction_query {
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+
+        match interaction {
+            Interaction::Pressed => match *action {
+                PauseButtonAction::Resume => {
+                    pause_state.set(PauseState::Running);
+                }
+                PauseButtonAction::Restart => {
+                    app_state.set(AppState::Restart);
+                    game_state.set(GameState::None);
+                }
+                PauseButtonAction::Resign => {
+                    resign_events.send(ResignEvent(acting_player));
+                    pause_state.set(PauseState::Running);
+                }
+                PauseButtonAction::OfferDraw => {
+                    draw_offer_events.send(DrawOfferEvent(acting_player));
+                    pause_state.set(PauseState::Running);
+                }
+                PauseButtonAction::Quit => {
+                    app_state.set(AppState::Menu);
+                    game_state.set(GameState::None);
+                }
+            },
+            Interaction::Hovered => {
+                text.sections[0].value = format!("> {action} <");
+            }
+            Interaction::None => {
+                text.sections[0].value = action.to_string();
+            }
+        }
+    }
+}
+
+/// The human the pause menu acts for: against the CPU it's always the human, even mid CPU turn.
+fn acting_player(current_player: &CurrentPlayer, ai_players: &AiPlayers) -> Player {
+    ai_players.human().unwrap_or(current_player.0)
+}
+
+fn reset(mut pause_state: ResMut<NextState<PauseState>>) {
+    pause_state.set(PauseState::Running);
+}
@@ -0,0 +1,347 @@
+use super::{
+    ai::{self, endgame::Endgames, AiPlayers},
+    helpers,
+    rules::Position,
+    Board, CurrentPlayer, EndReason, GameState, Player, Slot, Winner,
+};
+use crate::{
+    menu::Selected,
+    profile::Profiles,
+    states::{AppState, GameMode},
+    ui::UiAssets,
+};
+use bevy::{
+    prelude::*,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+};
+use bevy_persistent::Persistent;
+use std::fmt;
+
+const PROMPT_ALPHA: f32 = 0.7;
+const NOTICE_DURATION: f32 = 2.;
+
+pub struct ResignPlugin;
+
+impl Plugin for ResignPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DrawOffer>()
+            .init_resource::<DrawReply>()
+            .add_event::<ResignEvent>()
+            .add_event::<DrawOfferEvent>()
+            .add_systems(
+                Update,
+                (
+                    handle_resign,
+                    handle_draw_offer,
+                    reply_to_offer,
+                    button_action.run_if(pending_offer),
+                    expire_notices,
+                )
+                    .run_if(in_state(AppState::Game)),
+            )
+            // a game that ended some other way leaves nothing to answer
+            .add_systems(
+                OnEnter(GameState::Over),
+                (reset, helpers::despawn::<DrawPrompt>),
+            )
+            .add_systems(
+                OnExit(AppState::Game),
+                (
+                    reset,
+                    helpers::despawn::<DrawPrompt>,
+                    helpers::despawn::<Notice>,
+                ),
+            );
+    }
+}
+
+/// Sent when `0` gives up, ending the game in their opponent's favour.
+#[derive(Event)]
+pub struct ResignEvent(pub Player);
+
+/// Sent when `0` offers their opponent a draw.
+#[derive(Event)]
+pub struct DrawOfferEvent(pub Player);
+
+/// The player whose draw offer is waiting on a reply from the other human, if any.
+#[derive(Resource, Default)]
+pub struct DrawOffer(pub Option<Player>);
+
+/// The CPU making up its mind about a draw offered to it, which can take a deep search.
+#[derive(Resource, Default)]
+pub struct DrawReply(Option<(Player, Task<bool>)>);
+
+#[derive(Component)]
+struct DrawPrompt;
+
+#[derive(Component, Clone, Copy)]
+enum DrawPromptAction {
+    Accept,
+    Decline,
+}
+
+impl fmt::Display for DrawPromptAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Accept => write!(f, "ACCEPT"),
+            Self::Decline => write!(f, "DECLINE"),
+        }
+    }
+}
+
+#[derive(Component)]
+struct Notice(Timer);
+
+pub fn pending_offer(offer: Res<DrawOffer>, reply: Res<DrawReply>) -> bool {
+    offer.0.is_some() || reply.0.is_some()
+}
+
+fn end_game(
+    winner: &mut Winner,
+    game_state: &mut NextState<GameState>,
+    player: Option<Player>,
+    reason: EndReason,
+) {
+    winner.player = player;
+    winner.reason = reason;
+    game_state.set(GameState::Over);
+}
+
+fn handle_resign(
+    mut resign_events: EventReader<ResignEvent>,
+    mut winner: ResMut<Winner>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for ResignEvent(player) in resign_events.read() {
+        end_game(
+            &mut winner,
+            &mut game_state,
+            Some(player.flip()),
+            EndReason::Resignation,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_draw_offer(
+    mut commands: Commands,
+    mut draw_offer_events: EventReader<DrawOfferEvent>,
+    mut offer: ResMut<DrawOffer>,
+    mut reply: ResMut<DrawReply>,
+    ai_players: Res<AiPlayers>,
+    current_player: Res<CurrentPlayer>,
+    game_mode: Res<State<GameMode>>,
+    endgames: Res<Endgames>,
+    slot_query: Query<&Slot>,
+    ui_assets: Res<UiAssets>,
+) {
+    for DrawOfferEvent(player) in draw_offer_events.read() {
+        if offer.0.is_some() || reply.0.is_some() {
+            continue;
+        }
+
+        let opponent = player.flip();
+
+        let Some(difficulty) = ai_players.0[opponent as usize] else {
+            // the other human has to answer for themselves
+            offer.0 = Some(*player);
+            spawn_prompt(&mut commands, &ui_assets, opponent);
+            continue;
+        };
+
+        let mut position = Position {
+            counts: [0; Board::LENGTH],
+            current: current_player.0,
+        };
+
+        for slot in &slot_query {
+            position.counts[slot.index] = slot.count;
+        }
+
+        let game_mode = *game_mode.get();
+        let endgame = endgames.get(game_mode);
+
+        reply.0 = Some((
+            opponent,
+            AsyncComputeTaskPool::get().spawn(async move {
+                ai::accepts_draw(
+                    &position,
+                    opponent,
+                    game_mode,
+                    difficulty,
+                    endgame.as_deref(),
+                )
+            }),
+        ));
+    }
+}
+
+fn reply_to_offer(
+    mut commands: Commands,
+    mut reply: ResMut<DrawReply>,
+    mut winner: ResMut<Winner>,
+    mut game_state: ResMut<NextState<GameState>>,
+    selected: Res<Selected>,
+    profiles: Res<Persistent<Profiles>>,
+    ui_assets: Res<UiAssets>,
+) {
+    let Some((opponent, task)) = &mut reply.0 else {
+        return;
+    };
+
+    let Some(accepted) = block_on(future::poll_once(task)) else {
+        return;
+    };
+
+    if accepted {
+        end_game(&mut winner, &mut game_state, None, EndReason::Agreement);
+    } else {
+        let name = &profiles.0[selected.get(*opponent)].name;
+
+        spawn_notice(&mut commands, &ui_assets, &format!("{name} DECLINES"));
+    }
+
+    reply.0 = None;
+}
+
+fn spawn_prompt(commands: &mut Commands, ui_assets: &UiAssets, opponent: Player) {
+    let screen = helpers::get_screen(commands);
+
+    commands.entity(screen).insert((
+        DrawPrompt,
+        BackgroundColor(Color::rgba(0.0, 0.0, 0.0, PROMPT_ALPHA)),
+        ZIndex::Global(1),
+    ));
+
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            format!("{opponent}, ACCEPT A DRAW?"),
+            TextStyle {
+                font: ui_assets.font.clone(),
+                font_size: 40.0,
+                color: Color::WHITE,
+            },
+        ));
+
+        for action in [DrawPromptAction::Accept, DrawPromptAction::Decline] {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::top(Val::Px(20.)),
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    },
+                    action,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        action.to_string(),
+                        TextStyle {
+                            font: ui_assets.font.clone(),
+                            font_size: 40.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+fn spawn_notice(commands: &mut Commands, ui_assets: &UiAssets, value: &str) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Px(20.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            Notice(Timer::from_seconds(NOTICE_DURATION, TimerMode::Once)),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                value,
+                TextStyle {
+                    font: ui_assets.font.clone(),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+fn expire_notices(
+    mut commands: Commands,
+    mut notice_query: Query<(Entity, &mut Notice)>,
+    time: Res<Time>,
+) {
+    for (entity, mut notice) in &mut notice_query {
+        if notice.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn button_action(
+    mut commands: Commands,
+    interaction_query: Query<
+        (&Children, &Interaction, &DrawPromptAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+    prompt_query: Query<Entity, With<DrawPrompt>>,
+    mut text_query: Query<&mut Text>,
+    mut offer: ResMut<DrawOffer>,
+    mut winner: ResMut<Winner>,
+    mut game_state: ResMut<NextState<GameState>>,
+    ui_assets: Res<UiAssets>,
+) {
+    for (children, interaction, action) in &interaction_query {
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+
+        match interaction {
+            Interaction::Pressed => {
+                let Some(player) = offer.0.take() else {
+                    return;
+                };
+
+                match *action {
+                    DrawPromptAction::Accept => {
+                        end_game(&mut winner, &mut game_state, None, EndReason::Agreement);
+                    }
+                    DrawPromptAction::Decline => {
+                        let value = format!("{} DECLINES", player.flip());
+                        spawn_notice(&mut commands, &ui_assets, &value);
+                    }
+                }
+
+                for entity in &prompt_query {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = format!("> {action} <");
+            }
+            Interaction::None => {
+                text.sections[0].value = action.to_string();
+            }
+        }
+    }
+}
+
+fn reset(mut offer: ResMut<DrawOffer>, mut reply: ResMut<DrawReply>) {
+    offer.0 = None;
+    reply.0 = None;
+}
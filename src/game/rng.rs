@@ -0,0 +1,113 @@
+//! The one source of randomness for everything that shows up in a game, from the CPU's moves to
+//! where marbles land, so a run can be reproduced from its seed.
+//!
+//! The seed is taken from `--seed <N>` on the command line, then from `seed` in `settings.ron`,
+//! and is otherwise picked at random. Either way it's logged at startup.
+//!
+//! Every system drawing from it gets its own stream, derived from the seed, so what one draws
+//! doesn't depend on whether another happened to run first in the same frame.
+
+use crate::profile::ConfigDir;
+use bevy::prelude::*;
+use bevy_persistent::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// The golden ratio in 64 bits, spreading stream numbers over the whole seed.
+const STREAM_MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct RngSettings {
+    /// Seeds every run the same, unless `--seed` is given.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+fn setup(mut commands: Commands, config_dir: Res<ConfigDir>) {
+    let settings = Persistent::<RngSettings>::builder()
+        .name("settings")
+        .format(StorageFormat::RonPrettyWithStructNames)
+        .path(config_dir.0.join("settings.ron"))
+        .default(RngSettings::default())
+        .build()
+        .expect("failed to initialize settings");
+
+    let seed = seed_from_args()
+        .or(settings.seed)
+        .unwrap_or_else(rand::random);
+
+    info!("random seed is {seed}");
+
+    commands.insert_resource(GameRng::new(seed));
+    commands.insert_resource(settings);
+}
+
+fn seed_from_args() -> Option<u64> {
+    let mut args = env::args().skip_while(|arg| arg != "--seed").skip(1);
+
+    match args.next()?.parse() {
+        Ok(seed) => Some(seed),
+        Err(error) => {
+            warn!("ignoring --seed: {error}");
+            None
+        }
+    }
+}
+
+/// Who's drawing, each from a stream of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Where marbles land in their slot.
+    Marbles,
+    /// Where captured marbles land in the store.
+    Captures,
+    /// Where sown marbles land in the store.
+    Moves,
+    /// The move played for someone out of time.
+    Clock,
+    /// The CPU's seeds and fallback moves.
+    Ai,
+    /// The rules and opening of the demo game.
+    Attract,
+}
+
+impl Stream {
+    const COUNT: usize = 6;
+}
+
+/// Drawn from by anything random in a game, one stream per `Stream`.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    streams: [StdRng; Stream::COUNT],
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            // mixed in by a large odd multiple, so streams of neighbouring seeds don't line up
+            streams: std::array::from_fn(|index| {
+                StdRng::seed_from_u64(seed ^ (index as u64 + 1).wrapping_mul(STREAM_MIX))
+            }),
+        }
+    }
+
+    /// What the run was started with, to be passed back in with `--seed`.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: Stream) -> &mut StdRng {
+        &mut self.streams[stream as usize]
+    }
+}
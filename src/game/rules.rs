@@ -0,0 +1,347 @@
+use super::{Board, Player, SLOT_START_AMOUNT};
+use crate::states::GameMode;
+use std::cmp::Ordering;
+
+/// Seeds moved from `pits` into `store`, all at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    pub pits: Vec<usize>,
+    pub store: usize,
+}
+
+/// Everything that happened during a single move, in the order it happened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Outcome {
+    /// Each lap of sowing, starting with the pit that was emptied.
+    pub laps: Vec<Vec<usize>>,
+    pub capture: Option<Capture>,
+    pub captured: u32,
+    pub extra_turn: bool,
+}
+
+/// The board without any entities attached, so games can be replayed and searched.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub counts: [u32; Board::LENGTH],
+    pub current: Player,
+}
+
+impl Position {
+    pub fn new(starting_player: Player) -> Self {
+        let mut counts = [SLOT_START_AMOUNT; Board::LENGTH];
+
+        counts[Board::STORE_1] = 0;
+        counts[Board::STORE_2] = 0;
+
+        Self {
+            counts,
+            current: starting_player,
+        }
+    }
+
+    pub fn is_legal(&self, index: usize) -> bool {
+        Board::get_slots(self.current).contains(&index) && self.counts[index] > 0
+    }
+
+    pub fn legal_moves(&self) -> impl Iterator<Item = usize> + '_ {
+        Board::get_slots(self.current).filter(|index| self.counts[*index] > 0)
+    }
+
+    pub fn store(&self, player: Player) -> u32 {
+        self.counts[Board::get_store(player)]
+    }
+
+    /// Whether either side has run out of seeds, which ends the game.
+    pub fn is_over(&self) -> bool {
+        Player::iter().any(|player| Board::get_slots(player).all(|index| self.counts[index] == 0))
+    }
+
+    /// Plays the pit at `index` for the current player, which must be a legal move.
+    pub fn play(&mut self, index: usize, game_mode: GameMode) -> Outcome {
+        let player = self.current;
+        let mut outcome = Outcome::default();
+        let mut index = index;
+
+        loop {
+            let mut stack = self.counts[index];
+            let mut lap = vec![index];
+
+            self.counts[index] = 0;
+
+            while stack > 0 {
+                index = (index + 1) % Board::LENGTH;
+
+                if index == Board::get_store(player.flip()) {
+                    // skip the opponent's store
+                    continue;
+                }
+
+                self.counts[index] += 1;
+                stack -= 1;
+
+                lap.push(index);
+            }
+
+            outcome.laps.push(lap);
+
+            // Every lap sows at least one seed, and sowing only skips the opponent's store, so any
+            // `LENGTH - 1` seeds sown in a row put one in the mover's store. That store never loses
+            // seeds and landing in it ends the move, so a move sows fewer than `LENGTH - 1` seeds
+            // for every seed on the board plus one, and an avalanche can't go round forever.
+            debug_assert!(
+                outcome.laps.len()
+                    < (Board::LENGTH - 1) * (self.counts.iter().sum::<u32>() as usize + 1),
+                "avalanche went round more laps than it can"
+            );
+
+            if index == Board::get_store(player) {
+                break;
+            }
+
+            match game_mode {
+                GameMode::Capture => {
+                    let opposite = Board::LENGTH - index - 2;
+
+                    // capturing keeps the turn, just like landing in your own store
+                    if self.counts[index] == 1
+                        && Board::owner(index) == player
+                        && self.counts[opposite] > 0
+                    {
+                        let store = Board::get_store(player);
+                        let seeds = self.counts[opposite] + 1;
+
+                        self.counts[store] += seeds;
+                        self.counts[opposite] = 0;
+                        self.counts[index] = 0;
+
+                        outcome.captured = seeds;
+                        outcome.capture = Some(Capture {
+                            pits: vec![index, opposite],
+                            store,
+                        });
+                    } else {
+                        self.current = player.flip();
+                    }
+
+                    break;
+                }
+                GameMode::Avalanche => {
+                    if self.counts[index] > 1 {
+                        continue;
+                    }
+
+                    self.current = player.flip();
+
+                    break;
+                }
+            }
+        }
+
+        outcome.extra_turn = self.current == player;
+
+        outcome
+    }
+
+    /// In capture mode, whoever still has seeds when the game ends keeps them.
+    /// Returns the player who swept and how many seeds went to their store.
+    pub fn sweep(&mut self, game_mode: GameMode) -> Option<(Player, u32)> {
+        if game_mode != GameMode::Capture || !self.is_over() {
+            return None;
+        }
+
+        let player = Player::iter()
+            .find(|player| Board::get_slots(*player).any(|index| self.counts[index] > 0))?;
+
+        let seeds = Board::get_slots(player)
+            .map(|index| std::mem::take(&mut self.counts[index]))
+            .sum();
+
+        self.counts[Board::get_store(player)] += seeds;
+
+        Some((player, seeds))
+    }
+
+    /// The player with more seeds in their store, if any.
+    pub fn leader(&self) -> Option<Player> {
+        match self.store(Player::One).cmp(&self.store(Player::Two)) {
+            Ordering::Greater => Some(Player::One),
+            Ordering::Less => Some(Player::Two),
+            Ordering::Equal => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::panic::{self, AssertUnwindSafe};
+
+    const GAMES: u32 = 2000;
+    /// More moves than any game takes, so a game still going after this many never ends.
+    const MAX_MOVES: usize = 1000;
+    const SEEDS: u32 = SLOT_START_AMOUNT * (Board::LENGTH as u32 - 2);
+
+    /// A broken rule, with the pits played up to and including the move that broke it.
+    struct Failure {
+        moves: Vec<usize>,
+        reason: String,
+    }
+
+    /// Plays a game where each move is the legal move picked by the remainder of its choice, and
+    /// the first legal move once they run out, checking the rules after every move.
+    fn check(
+        game_mode: GameMode,
+        starting_player: Player,
+        choices: &[usize],
+    ) -> Result<(), Failure> {
+        let mut position = Position::new(starting_player);
+        let mut moves = vec![];
+
+        for turn in 0..MAX_MOVES {
+            if position.is_over() {
+                return Ok(());
+            }
+
+            let legal: Vec<usize> = position.legal_moves().collect();
+            let index = legal[choices.get(turn).copied().unwrap_or(0) % legal.len()];
+            let player = position.current;
+            let other_store = Board::get_store(player.flip());
+            let before = position.counts;
+
+            moves.push(index);
+
+            let fail = |reason: String| Failure {
+                moves: moves.clone(),
+                reason,
+            };
+
+            // a panicking move, like an avalanche going round more than it can, is reported like
+            // any other broken rule
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| position.play(index, game_mode)))
+                .map_err(|_| fail("the move panicked".to_string()))?;
+
+            let total: u64 = position.counts.iter().map(|count| u64::from(*count)).sum();
+
+            if total != u64::from(SEEDS) {
+                return Err(fail(format!(
+                    "{total} seeds on the board instead of {SEEDS}"
+                )));
+            }
+
+            for (pit, after) in position.counts.iter().copied().enumerate() {
+                let sown = outcome
+                    .laps
+                    .iter()
+                    .flat_map(|lap| &lap[1..])
+                    .filter(|sown| **sown == pit)
+                    .count() as u32;
+                let capture = outcome.capture.as_ref();
+
+                // a pit picked up may be sown into again afterwards, but never by more than it got
+                let (least, most) = if capture.is_some_and(|capture| capture.pits.contains(&pit)) {
+                    (0, 0)
+                } else if capture.is_some_and(|capture| capture.store == pit) {
+                    let total = before[pit] + sown + outcome.captured;
+                    (total, total)
+                } else if outcome.laps.iter().any(|lap| lap[0] == pit) {
+                    (0, sown)
+                } else {
+                    (before[pit] + sown, before[pit] + sown)
+                };
+
+                if !(least..=most).contains(&after) {
+                    return Err(fail(format!(
+                        "pit {pit} went from {} to {after} instead of {least} to {most}",
+                        before[pit]
+                    )));
+                }
+            }
+
+            if outcome.laps.iter().flatten().any(|pit| *pit == other_store)
+                || position.counts[other_store] != before[other_store]
+            {
+                return Err(fail("sowed into the opponent's store".to_string()));
+            }
+        }
+
+        if position.is_over() {
+            Ok(())
+        } else {
+            Err(Failure {
+                moves,
+                reason: format!("the game didn't end within {MAX_MOVES} moves"),
+            })
+        }
+    }
+
+    /// Drops and lowers choices for as long as the game still fails, so it's easier to follow.
+    fn shrink(
+        game_mode: GameMode,
+        starting_player: Player,
+        mut choices: Vec<usize>,
+        mut failure: Failure,
+    ) -> Failure {
+        loop {
+            // nothing after the failing move matters
+            choices.truncate(failure.moves.len());
+
+            let smaller = (0..choices.len())
+                .rev()
+                .flat_map(|turn| {
+                    let mut dropped = choices.clone();
+                    dropped.remove(turn);
+
+                    let mut lowered = choices.clone();
+                    lowered[turn] = 0;
+
+                    [dropped, lowered]
+                })
+                .filter(|candidate| *candidate != choices)
+                .find_map(|candidate| {
+                    check(game_mode, starting_player, &candidate)
+                        .err()
+                        .map(|failure| (candidate, failure))
+                });
+
+            match smaller {
+                Some((candidate, smaller)) => {
+                    choices = candidate;
+                    failure = smaller;
+                }
+                None => return failure,
+            }
+        }
+    }
+
+    /// Plays thousands of random games, reporting the shortest game it can find that breaks a rule.
+    fn check_random_games(game_mode: GameMode, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        for _ in 0..GAMES {
+            let starting_player = if rng.gen() { Player::One } else { Player::Two };
+            let choices: Vec<usize> = (0..MAX_MOVES)
+                .map(|_| rng.gen_range(0..Board::COLS))
+                .collect();
+
+            if let Err(failure) = check(game_mode, starting_player, &choices) {
+                let failure = shrink(game_mode, starting_player, choices, failure);
+
+                panic!(
+                    "{game_mode} with {starting_player} first: {} after playing {:?}",
+                    failure.reason, failure.moves
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn avalanche_games_keep_the_rules() {
+        check_random_games(GameMode::Avalanche, 50);
+    }
+
+    #[test]
+    fn capture_games_keep_the_rules() {
+        check_random_games(GameMode::Capture, 50);
+    }
+}
@@ -0,0 +1,122 @@
+use super::{ai::AiPlayers, attract::Attract, helpers};
+use crate::{states::AppState, ui::UiAssets};
+use bevy::prelude::*;
+
+/// How many times faster than normal a game between two CPUs can be watched.
+const SPEEDS: [f32; 4] = [1., 2., 4., 8.];
+
+pub struct SpeedPlugin;
+
+impl Plugin for SpeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameSpeed>()
+            .add_systems(OnEnter(AppState::Game), setup)
+            .add_systems(Update, button_action.run_if(in_state(AppState::Game)))
+            .add_systems(
+                OnExit(AppState::Game),
+                (helpers::despawn::<SpeedControl>, reset),
+            );
+    }
+}
+
+/// An index into `SPEEDS`, kept between games so a long demo doesn't need setting up again.
+#[derive(Resource, Default)]
+struct GameSpeed(usize);
+
+impl GameSpeed {
+    fn get(&self) -> f32 {
+        SPEEDS[self.0]
+    }
+}
+
+#[derive(Component)]
+struct SpeedControl;
+
+fn label(speed: &GameSpeed) -> String {
+    format!("SPEED: {}X", speed.get())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut time: ResMut<Time<Virtual>>,
+    speed: Res<GameSpeed>,
+    ai_players: Res<AiPlayers>,
+    attract: Option<Res<Attract>>,
+    ui_assets: Res<UiAssets>,
+) {
+    // speeding up is only fair when nobody is playing, and the demo plays at its own pace
+    if !ai_players.is_spectating() || attract.is_some() {
+        return;
+    }
+
+    time.set_relative_speed(speed.get());
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.),
+                    bottom: Val::Px(20.),
+                    ..default()
+                },
+                ..default()
+            },
+            SpeedControl,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(ButtonBundle {
+                    background_color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        label(&speed),
+                        TextStyle {
+                            font: ui_assets.font.clone(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        });
+}
+
+fn button_action(
+    interaction_query: Query<(&Children, &Interaction, &Parent), Changed<Interaction>>,
+    control_query: Query<(), With<SpeedControl>>,
+    mut text_query: Query<&mut Text>,
+    mut time: ResMut<Time<Virtual>>,
+    mut speed: ResMut<GameSpeed>,
+) {
+    for (children, interaction, parent) in &interaction_query {
+        if !control_query.contains(parent.get()) {
+            continue;
+        }
+
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+
+        match interaction {
+            Interaction::Pressed => {
+                speed.0 = (speed.0 + 1) % SPEEDS.len();
+                time.set_relative_speed(speed.get());
+
+                // still hovered, so keep the arrows
+                text.sections[0].value = format!("> {} <", label(&speed));
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = format!("> {} <", label(&speed));
+            }
+            Interaction::None => {
+                text.sections[0].value = label(&speed);
+            }
+        }
+    }
+}
+
+fn reset(mut time: ResMut<Time<Virtual>>) {
+    time.set_relative_speed(1.);
+}
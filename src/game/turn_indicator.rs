@@ -1,7 +1,9 @@
 use super::{
     animation::AnimationState,
     board::{BOARD_HEIGHT, BOARD_WIDTH},
+    clock::{Clock, TimeControl},
     helpers,
+    network::Session,
 };
 use crate::{
     game::{CurrentPlayer, Player},
@@ -40,12 +42,32 @@ pub struct TurnIndicatorLabel {
     pub player: Player,
 }
 
+impl TurnIndicatorLabel {
+    fn value(&self, current_player: Player, clock: &Clock, time_control: TimeControl) -> String {
+        let value = if time_control == TimeControl::Untimed {
+            self.name.clone()
+        } else {
+            format!("{} {}", self.name, clock.format(self.player as usize))
+        };
+
+        if self.player == current_player {
+            format!("> {} <", value)
+        } else {
+            value
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn draw_labels(
     mut commands: Commands,
     ui_assets: Res<UiAssets>,
     current_player: Res<CurrentPlayer>,
     selected: Res<Selected>,
     profiles: Res<Persistent<Profiles>>,
+    clock: Res<Clock>,
+    time_control: Res<TimeControl>,
+    session: Option<Res<Session>>,
 ) {
     let screen = helpers::get_screen(&mut commands);
 
@@ -67,22 +89,17 @@ pub fn draw_labels(
                 .id();
 
             // TODO: better way of handling this?
-            let name = profiles.0[selected.get(player)].name.clone();
-
-            let value = {
-                if player == current_player.0 {
-                    format!("> {} <", name)
-                } else {
-                    name.clone()
-                }
+            let name = match &session {
+                Some(session) => session.names[player as usize].clone(),
+                None => profiles.0[selected.get(player)].name.clone(),
             };
 
+            let indicator = TurnIndicatorLabel { name, player };
+            let value = indicator.value(current_player.0, &clock, *time_control);
+
             let label = helpers::get_text(&mut commands, ui_assets.as_ref(), &value);
 
-            commands.entity(label).insert(TurnIndicatorLabel {
-                name: name.clone(),
-                player,
-            });
+            commands.entity(label).insert(indicator);
             commands.entity(container).push_children(&[label]);
 
             container
@@ -110,16 +127,16 @@ pub fn update_labels(
     mut text_query: Query<(&mut Text, &TurnIndicatorLabel)>,
     current_player: Res<CurrentPlayer>,
     animation_state: Res<State<AnimationState>>,
+    clock: Res<Clock>,
+    time_control: Res<TimeControl>,
 ) {
-    if !animation_state.is_changed() || *animation_state.get() == AnimationState::Animating {
+    if !(animation_state.is_changed() || clock.is_changed())
+        || *animation_state.get() == AnimationState::Animating
+    {
         return;
     }
 
     for (mut text, label) in &mut text_query {
-        if label.player == current_player.0 {
-            text.sections[0].value = format!("> {} <", label.name.clone());
-        } else {
-            text.sections[0].value = label.name.clone();
-        }
+        text.sections[0].value = label.value(current_player.0, &clock, *time_control);
     }
 }
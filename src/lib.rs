@@ -0,0 +1,5 @@
+pub mod game;
+pub mod menu;
+pub mod profile;
+pub mod states;
+pub mod ui;
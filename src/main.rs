@@ -1,10 +1,5 @@
 use bevy::prelude::*;
-
-mod game;
-mod menu;
-mod profile;
-mod states;
-mod ui;
+use bevy_mancala::{game, menu, profile, states, ui};
 
 // (30, 48, 51)
 const BACKGROUND_COLOR_MAIN: Color = Color::rgb(0.11764706, 0.1882353, 0.2);
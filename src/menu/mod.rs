@@ -1,6 +1,15 @@
 use crate::{
-    game::{ai::AI_NAME, Player},
-    profile::Profiles,
+    game::{
+        ai::CpuDifficulty,
+        clock::TimeControl,
+        correspondence::{self, CorrespondenceEvent, MailStatus},
+        network::{
+            discovery::{Discovered, DiscoveredGame},
+            Lobby, LobbyEvent,
+        },
+        Player, StartingPlayer,
+    },
+    profile::{ConfigDir, ProfileKind, Profiles},
     states::{AppState, GameMode},
     ui::UiAssets,
 };
@@ -9,6 +18,7 @@ use bevy::{
     prelude::*,
 };
 use bevy_persistent::Persistent;
+use std::{fs, net::SocketAddr, path::PathBuf};
 
 const PROFILE_LIMIT: usize = 10;
 const PROFILE_SIZE: f32 = 80.;
@@ -31,12 +41,21 @@ impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<MenuState>()
             .init_resource::<Selected>()
+            .init_resource::<Address>()
+            .init_resource::<MailFiles>()
             .add_systems(OnEnter(AppState::Menu), setup_start_screen)
             .add_systems(OnExit(AppState::Menu), despawn::<Main>)
             .add_systems(OnExit(MenuState::Start), despawn::<Hint>)
             .add_systems(OnEnter(MenuState::Mode), setup_mode_screen)
             .add_systems(OnExit(MenuState::Mode), despawn::<Mode>)
             .add_systems(OnEnter(MenuState::Profile), setup_profile_screen)
+            .add_systems(OnExit(MenuState::Profile), despawn::<Profile>)
+            .add_systems(OnEnter(MenuState::Lan), setup_lan_screen)
+            .add_systems(OnExit(MenuState::Lan), despawn::<Lan>)
+            .add_systems(OnEnter(MenuState::Browse), setup_browse_screen)
+            .add_systems(OnExit(MenuState::Browse), despawn::<Browse>)
+            .add_systems(OnEnter(MenuState::Mail), setup_mail_screen)
+            .add_systems(OnExit(MenuState::Mail), despawn::<Mail>)
             .add_systems(
                 Update,
                 (
@@ -45,6 +64,11 @@ impl Plugin for MenuPlugin {
                     (selected_changed, spawn_profiles)
                         .run_if(in_state(MenuState::Profile))
                         .after(setup_profile_screen),
+                    (type_address, update_lobby_status).run_if(in_state(MenuState::Lan)),
+                    spawn_discovered
+                        .run_if(in_state(MenuState::Browse))
+                        .after(setup_browse_screen),
+                    update_mail_status.run_if(in_state(MenuState::Mail)),
                 )
                     .run_if(in_state(AppState::Menu)),
             );
@@ -57,6 +81,9 @@ pub enum MenuState {
     Start,
     Mode,
     Profile,
+    Lan,
+    Browse,
+    Mail,
 }
 
 #[derive(Component)]
@@ -65,7 +92,22 @@ enum ButtonAction {
     SwapProfiles,
     AddProfile,
     SelectProfile(usize),
+    CycleTimeControl,
+    CycleDifficulty(Player),
     Play,
+    OpenLan,
+    Host,
+    Join,
+    Watch,
+    Back,
+    OpenBrowse,
+    JoinDiscovered(SocketAddr),
+    WatchDiscovered(SocketAddr),
+    BackToLan,
+    OpenMail,
+    NewMail,
+    OpenFile(usize),
+    BackToProfile,
 }
 
 #[derive(Component)]
@@ -75,14 +117,52 @@ struct Main;
 struct Hint;
 
 #[derive(Component)]
-struct Blink;
+pub struct Blink;
 
 #[derive(Component)]
 struct Mode;
 
+#[derive(Component)]
+struct Profile;
+
+#[derive(Component)]
+struct Lan;
+
+#[derive(Component)]
+struct Browse;
+
+#[derive(Component)]
+struct Mail;
+
+#[derive(Component)]
+struct UiGameList;
+
+#[derive(Component)]
+struct UiMailStatus;
+
 #[derive(Component)]
 struct UiProfileContainer;
 
+#[derive(Component)]
+struct UiAddress;
+
+#[derive(Component)]
+struct UiLobbyStatus;
+
+/// The address typed on the LAN screen, kept between visits.
+#[derive(Resource)]
+struct Address(String);
+
+impl Default for Address {
+    fn default() -> Self {
+        Self("127.0.0.1".to_string())
+    }
+}
+
+/// The game files listed on the mail screen, which buttons refer to by index.
+#[derive(Resource, Default)]
+struct MailFiles(Vec<PathBuf>);
+
 #[derive(Component)]
 struct UiSelected;
 
@@ -121,23 +201,27 @@ impl Selected {
 }
 
 fn setup_start_screen(mut commands: Commands, ui_assets: Res<UiAssets>) {
+    let screen = spawn_title_screen(&mut commands, &ui_assets);
+
+    commands.entity(screen).insert(Main);
+}
+
+/// The title and a blinking hint, shared by the start screen and the attract mode over it.
+pub fn spawn_title_screen(commands: &mut Commands, ui_assets: &UiAssets) -> Entity {
     let screen = commands
-        .spawn((
-            NodeBundle {
-                style: Style {
-                    display: Display::Flex,
-                    width: Val::Percent(100.),
-                    height: Val::Percent(100.),
-                    align_items: AlignItems::Center,
-                    justify_content: JustifyContent::Center,
-                    flex_direction: FlexDirection::Column,
-                    row_gap: Val::Px(20.),
-                    ..default()
-                },
+        .spawn(NodeBundle {
+            style: Style {
+                display: Display::Flex,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(20.),
                 ..default()
             },
-            Main,
-        ))
+            ..default()
+        })
         .id();
 
     let header = commands
@@ -205,9 +289,11 @@ fn setup_start_screen(mut commands: Commands, ui_assets: Res<UiAssets>) {
         .id();
 
     commands.entity(screen).push_children(&[header, hint]);
+
+    screen
 }
 
-fn blink(mut query: Query<&mut Text, With<Blink>>, time: Res<Time>) {
+pub fn blink(mut query: Query<&mut Text, With<Blink>>, time: Res<Time>) {
     for mut text in &mut query {
         let alpha = (time.elapsed_seconds() * 2.0).sin() * 0.5 + 0.5;
         text.sections[0].style.color = text.sections[0].style.color.with_a(alpha);
@@ -315,6 +401,8 @@ fn setup_profile_screen(
     query: Query<Entity, With<Main>>,
     ui_materials: Res<UiAssets>,
     mut selected: ResMut<Selected>,
+    time_control: Res<TimeControl>,
+    difficulty: Res<CpuDifficulty>,
 ) {
     let screen = query.single();
 
@@ -479,6 +567,58 @@ fn setup_profile_screen(
         ))
         .id();
 
+    let time = commands
+        .spawn((
+            ButtonBundle {
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            ButtonAction::CycleTimeControl,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!("TIME: {}", *time_control),
+                TextStyle {
+                    font: ui_materials.font.clone(),
+                    font_size: 40.0,
+                    color: TEXT_COLOR,
+                },
+            ));
+        })
+        .id();
+
+    let difficulties = commands
+        .spawn(NodeBundle {
+            style: Style {
+                column_gap: Val::Px(40.),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for player in Player::iter() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        ButtonAction::CycleDifficulty(player),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            difficulty_label(player, &difficulty),
+                            TextStyle {
+                                font: ui_materials.font.clone(),
+                                font_size: 40.0,
+                                color: TEXT_COLOR,
+                            },
+                        ));
+                    });
+            }
+        })
+        .id();
+
     let play = commands
         .spawn((
             ButtonBundle {
@@ -499,19 +639,80 @@ fn setup_profile_screen(
         })
         .id();
 
+    let lan = commands
+        .spawn((
+            ButtonBundle {
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            ButtonAction::OpenLan,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "LAN",
+                TextStyle {
+                    font: ui_materials.font.clone(),
+                    font_size: 40.0,
+                    color: TEXT_COLOR,
+                },
+            ));
+        })
+        .id();
+
     commands.entity(top_container).push_children(&[
         selected_profiles[0],
         swap,
         selected_profiles[1],
     ]);
 
-    commands
-        .entity(screen)
-        .push_children(&[title, top_container, profiles_container, play]);
+    let mail = commands
+        .spawn((
+            ButtonBundle {
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            ButtonAction::OpenMail,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "MAIL",
+                TextStyle {
+                    font: ui_materials.font.clone(),
+                    font_size: 40.0,
+                    color: TEXT_COLOR,
+                },
+            ));
+        })
+        .id();
+
+    let children = [
+        title,
+        top_container,
+        profiles_container,
+        time,
+        difficulties,
+        play,
+        lan,
+        mail,
+    ];
+
+    for child in children {
+        commands.entity(child).insert(Profile);
+    }
+
+    commands.entity(screen).push_children(&children);
 
     *selected = Selected::default();
 }
 
+fn difficulty_label(player: Player, difficulty: &CpuDifficulty) -> String {
+    format!(
+        "CPU {}: {}",
+        player as usize + 1,
+        difficulty.0[player as usize]
+    )
+}
+
 fn spawn_profiles(
     mut commands: Commands,
     mut profile_container: Query<Entity, With<UiProfileContainer>>,
@@ -533,7 +734,7 @@ fn spawn_profiles(
     }
 
     for (index, profile) in profiles.0.iter().take(PROFILE_LIMIT).enumerate() {
-        let is_ai = profile.name == AI_NAME;
+        let is_ai = profile.is_ai();
 
         let (color, background_color) = if selected.is_selected(index) {
             if is_ai {
@@ -575,7 +776,11 @@ fn spawn_profiles(
                     aspect_ratio: Some(1.0),
                     ..default()
                 },
-                image: ui_assets.profile.clone().into(),
+                image: match profile.kind {
+                    ProfileKind::Personality(personality) => ui_assets.avatar(personality),
+                    _ => ui_assets.profile.clone(),
+                }
+                .into(),
                 ..default()
             })
             .id();
@@ -630,6 +835,406 @@ fn spawn_profiles(
     }
 }
 
+fn setup_lan_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<Main>>,
+    ui_materials: Res<UiAssets>,
+    address: Res<Address>,
+    lobby: Res<Lobby>,
+) {
+    let screen = query.single();
+
+    let text_style = TextStyle {
+        font: ui_materials.font.clone(),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    let title = commands
+        .spawn((
+            TextBundle::from_section("LAN GAME", text_style.clone()),
+            Lan,
+        ))
+        .id();
+
+    let address = commands
+        .spawn((
+            TextBundle::from_section(format!("ADDRESS: {}_", address.0), text_style.clone()),
+            UiAddress,
+            Lan,
+        ))
+        .id();
+
+    let buttons: Vec<Entity> = [
+        (ButtonAction::Host, "HOST"),
+        (ButtonAction::Join, "JOIN"),
+        (ButtonAction::Watch, "WATCH"),
+        (ButtonAction::OpenBrowse, "FIND GAMES"),
+        (ButtonAction::Back, "BACK"),
+    ]
+    .into_iter()
+    .map(|(action, value)| {
+        commands
+            .spawn((
+                ButtonBundle {
+                    background_color: Color::NONE.into(),
+                    ..default()
+                },
+                action,
+                Lan,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(value, text_style.clone()));
+            })
+            .id()
+    })
+    .collect();
+
+    let status = commands
+        .spawn((
+            TextBundle::from_section(lobby.status(), text_style.clone()),
+            UiLobbyStatus,
+            Lan,
+        ))
+        .id();
+
+    commands.entity(screen).push_children(&[title, address]);
+    commands.entity(screen).push_children(&buttons);
+    commands.entity(screen).push_children(&[status]);
+}
+
+fn type_address(
+    mut received_characters: EventReader<ReceivedCharacter>,
+    mut text_query: Query<&mut Text, With<UiAddress>>,
+    mut address: ResMut<Address>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    for event in received_characters.read() {
+        address.0.extend(
+            event
+                .char
+                .chars()
+                .filter(|char| char.is_ascii_alphanumeric() || matches!(char, '.' | ':' | '-')),
+        );
+    }
+
+    if keyboard.just_pressed(KeyCode::Backspace) {
+        address.0.pop();
+    }
+
+    if !address.is_changed() {
+        return;
+    }
+
+    for mut text in &mut text_query {
+        text.sections[0].value = format!("ADDRESS: {}_", address.0);
+    }
+}
+
+fn update_lobby_status(mut text_query: Query<&mut Text, With<UiLobbyStatus>>, lobby: Res<Lobby>) {
+    if !lobby.is_changed() {
+        return;
+    }
+
+    for mut text in &mut text_query {
+        text.sections[0].value = lobby.status();
+    }
+}
+
+fn setup_browse_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<Main>>,
+    ui_materials: Res<UiAssets>,
+) {
+    let screen = query.single();
+
+    let text_style = TextStyle {
+        font: ui_materials.font.clone(),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    let title = commands
+        .spawn((
+            TextBundle::from_section("JOIN LAN GAME", text_style.clone()),
+            Browse,
+        ))
+        .id();
+
+    let list = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::Flex,
+                    width: Val::Px(PROFILE_CONTAINER_WIDTH),
+                    padding: UiRect::all(Val::Px(10.)),
+                    row_gap: Val::Px(10.),
+                    align_items: AlignItems::Center,
+                    flex_direction: FlexDirection::Column,
+                    border: UiRect::all(Val::Px(5.)),
+                    ..default()
+                },
+                background_color: PRIMARY_COLOR.into(),
+                border_color: ACCENT_COLOR.into(),
+                ..default()
+            },
+            UiGameList,
+            Browse,
+        ))
+        .id();
+
+    let back = commands
+        .spawn((
+            ButtonBundle {
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            ButtonAction::BackToLan,
+            Browse,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("BACK", text_style.clone()));
+        })
+        .id();
+
+    commands.entity(screen).push_children(&[title, list, back]);
+}
+
+fn spawn_discovered(
+    mut commands: Commands,
+    query: Query<Entity, With<UiGameList>>,
+    ui_materials: Res<UiAssets>,
+    discovered: Res<Discovered>,
+) {
+    let Ok(list) = query.get_single() else {
+        return;
+    };
+
+    if !discovered.is_changed() {
+        return;
+    }
+
+    commands.entity(list).despawn_descendants();
+
+    let text_style = TextStyle {
+        font: ui_materials.font.clone(),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    if discovered.games.is_empty() {
+        let value = if discovered.listening {
+            "SEARCHING..."
+        } else {
+            "CAN'T SEARCH HERE"
+        };
+
+        let text = commands
+            .spawn(TextBundle::from_section(value, text_style))
+            .id();
+
+        commands.entity(list).add_child(text);
+
+        return;
+    }
+
+    for DiscoveredGame {
+        address, advert, ..
+    } in &discovered.games
+    {
+        let value = format!(
+            "{} - {} - {}",
+            advert.name,
+            advert.mode,
+            if advert.open { "OPEN" } else { "FULL" }
+        );
+
+        // full games can still be watched
+        let (action, color) = if advert.open {
+            (ButtonAction::JoinDiscovered(*address), TEXT_COLOR)
+        } else {
+            (ButtonAction::WatchDiscovered(*address), ACCENT_ALT_COLOR)
+        };
+
+        let entry = commands
+            .spawn((
+                ButtonBundle {
+                    background_color: Color::NONE.into(),
+                    ..default()
+                },
+                action,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    value,
+                    TextStyle {
+                        color,
+                        ..text_style.clone()
+                    },
+                ));
+            })
+            .id();
+
+        commands.entity(list).add_child(entry);
+    }
+}
+
+fn setup_mail_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<Main>>,
+    ui_materials: Res<UiAssets>,
+    mut mail_files: ResMut<MailFiles>,
+    mut status: ResMut<MailStatus>,
+    config_dir: Res<ConfigDir>,
+) {
+    let screen = query.single();
+    let inbox = correspondence::inbox(&config_dir);
+
+    let text_style = TextStyle {
+        font: ui_materials.font.clone(),
+        font_size: 40.0,
+        color: TEXT_COLOR,
+    };
+
+    // newest first, so the file that just arrived is at the top
+    let mut files: Vec<(PathBuf, std::time::SystemTime)> = fs::read_dir(&inbox)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "mancala")
+        })
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+            modified.ok().map(|modified| (path, modified))
+        })
+        .collect();
+
+    files.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    mail_files.0 = files.into_iter().take(6).map(|(path, _)| path).collect();
+    status.0.clear();
+
+    let title = commands
+        .spawn((
+            TextBundle::from_section("CORRESPONDENCE", text_style.clone()),
+            Mail,
+        ))
+        .id();
+
+    let new = commands
+        .spawn((
+            ButtonBundle {
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            ButtonAction::NewMail,
+            Mail,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("NEW GAME", text_style.clone()));
+        })
+        .id();
+
+    let list = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::Flex,
+                    width: Val::Px(PROFILE_CONTAINER_WIDTH),
+                    padding: UiRect::all(Val::Px(10.)),
+                    row_gap: Val::Px(10.),
+                    align_items: AlignItems::Center,
+                    flex_direction: FlexDirection::Column,
+                    border: UiRect::all(Val::Px(5.)),
+                    ..default()
+                },
+                background_color: PRIMARY_COLOR.into(),
+                border_color: ACCENT_COLOR.into(),
+                ..default()
+            },
+            Mail,
+        ))
+        .with_children(|parent| {
+            let small = TextStyle {
+                font_size: 20.0,
+                ..text_style.clone()
+            };
+
+            if mail_files.0.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    format!("NO FILES IN {}", inbox.display()),
+                    small,
+                ));
+
+                return;
+            }
+
+            for (index, path) in mail_files.0.iter().enumerate() {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        ButtonAction::OpenFile(index),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(name, small.clone()));
+                    });
+            }
+        })
+        .id();
+
+    let status = commands
+        .spawn((
+            TextBundle::from_section(
+                status.0.clone(),
+                TextStyle {
+                    color: ACCENT_ALT_COLOR,
+                    ..text_style.clone()
+                },
+            ),
+            UiMailStatus,
+            Mail,
+        ))
+        .id();
+
+    let back = commands
+        .spawn((
+            ButtonBundle {
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            ButtonAction::BackToProfile,
+            Mail,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("BACK", text_style.clone()));
+        })
+        .id();
+
+    commands
+        .entity(screen)
+        .push_children(&[title, new, list, status, back]);
+}
+
+fn update_mail_status(
+    mut text_query: Query<&mut Text, With<UiMailStatus>>,
+    status: Res<MailStatus>,
+) {
+    if !status.is_changed() {
+        return;
+    }
+
+    for mut text in &mut text_query {
+        text.sections[0].value = status.0.clone();
+    }
+}
+
 fn selected_changed(
     mut text_query: Query<&mut Text, With<UiSelected>>,
     profiles: Res<Persistent<Profiles>>,
@@ -649,6 +1254,7 @@ fn selected_changed(
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn button_action(
     interaction_query: Query<
         (&Children, &Interaction, &ButtonAction),
@@ -659,6 +1265,13 @@ fn button_action(
     mut menu_state: ResMut<NextState<MenuState>>,
     mut game_state: ResMut<NextState<GameMode>>,
     mut selected: ResMut<Selected>,
+    mut starting_player: ResMut<StartingPlayer>,
+    mut time_control: ResMut<TimeControl>,
+    mut difficulty: ResMut<CpuDifficulty>,
+    mut lobby_events: EventWriter<LobbyEvent>,
+    mut address: ResMut<Address>,
+    mut correspondence_events: EventWriter<CorrespondenceEvent>,
+    mail_files: Res<MailFiles>,
 ) {
     for (children, interaction, action) in &interaction_query {
         match interaction {
@@ -680,14 +1293,98 @@ fn button_action(
                 ButtonAction::SelectProfile(index) => {
                     selected.select(index);
                 }
+                ButtonAction::CycleTimeControl => {
+                    *time_control = time_control.next();
+
+                    let Ok(mut text) = text_query.get_mut(children[0]) else {
+                        return;
+                    };
+                    // still hovered, so keep the arrows
+                    text.sections[0].value = format!("> TIME: {} <", *time_control);
+                }
+                ButtonAction::CycleDifficulty(player) => {
+                    let seat = player as usize;
+                    difficulty.0[seat] = difficulty.0[seat].next();
+
+                    let Ok(mut text) = text_query.get_mut(children[0]) else {
+                        return;
+                    };
+                    // still hovered, so keep the arrows
+                    text.sections[0].value =
+                        format!("> {} <", difficulty_label(player, &difficulty));
+                }
                 ButtonAction::Play => {
+                    starting_player.0 = Player::One;
                     app_state.set(AppState::Game);
                     menu_state.set(MenuState::Start);
                 }
+                ButtonAction::OpenLan => {
+                    menu_state.set(MenuState::Lan);
+                }
+                ButtonAction::Host => {
+                    lobby_events.send(LobbyEvent::Host);
+                }
+                ButtonAction::Join => {
+                    lobby_events.send(LobbyEvent::Join(address.0.clone()));
+                }
+                ButtonAction::Watch => {
+                    lobby_events.send(LobbyEvent::Watch(address.0.clone()));
+                }
+                ButtonAction::Back => {
+                    lobby_events.send(LobbyEvent::Cancel);
+                    menu_state.set(MenuState::Profile);
+                }
+                ButtonAction::OpenBrowse => {
+                    menu_state.set(MenuState::Browse);
+                }
+                ButtonAction::JoinDiscovered(game_address) => {
+                    // back on the lan screen the status shows how joining went
+                    address.0 = game_address.to_string();
+                    lobby_events.send(LobbyEvent::Join(address.0.clone()));
+                    menu_state.set(MenuState::Lan);
+                }
+                ButtonAction::WatchDiscovered(game_address) => {
+                    address.0 = game_address.to_string();
+                    lobby_events.send(LobbyEvent::Watch(address.0.clone()));
+                    menu_state.set(MenuState::Lan);
+                }
+                ButtonAction::BackToLan => {
+                    menu_state.set(MenuState::Lan);
+                }
+                ButtonAction::OpenMail => {
+                    menu_state.set(MenuState::Mail);
+                }
+                ButtonAction::NewMail => {
+                    correspondence_events.send(CorrespondenceEvent::New);
+                }
+                ButtonAction::OpenFile(index) => {
+                    if let Some(path) = mail_files.0.get(index) {
+                        correspondence_events.send(CorrespondenceEvent::Open(path.clone()));
+                    }
+                }
+                ButtonAction::BackToProfile => {
+                    menu_state.set(MenuState::Profile);
+                }
                 _ => {}
             },
             Interaction::Hovered => match *action {
-                ButtonAction::SelectMode(_) | ButtonAction::Play => {
+                ButtonAction::SelectMode(_)
+                | ButtonAction::CycleTimeControl
+                | ButtonAction::CycleDifficulty(_)
+                | ButtonAction::Play
+                | ButtonAction::OpenLan
+                | ButtonAction::Host
+                | ButtonAction::Join
+                | ButtonAction::Watch
+                | ButtonAction::Back
+                | ButtonAction::OpenBrowse
+                | ButtonAction::JoinDiscovered(_)
+                | ButtonAction::WatchDiscovered(_)
+                | ButtonAction::BackToLan
+                | ButtonAction::OpenMail
+                | ButtonAction::NewMail
+                | ButtonAction::OpenFile(_)
+                | ButtonAction::BackToProfile => {
                     let Ok(mut text) = text_query.get_mut(children[0]) else {
                         return;
                     };
@@ -696,7 +1393,23 @@ fn button_action(
                 _ => {}
             },
             Interaction::None => match *action {
-                ButtonAction::SelectMode(_) | ButtonAction::Play => {
+                ButtonAction::SelectMode(_)
+                | ButtonAction::CycleTimeControl
+                | ButtonAction::CycleDifficulty(_)
+                | ButtonAction::Play
+                | ButtonAction::OpenLan
+                | ButtonAction::Host
+                | ButtonAction::Join
+                | ButtonAction::Watch
+                | ButtonAction::Back
+                | ButtonAction::OpenBrowse
+                | ButtonAction::JoinDiscovered(_)
+                | ButtonAction::WatchDiscovered(_)
+                | ButtonAction::BackToLan
+                | ButtonAction::OpenMail
+                | ButtonAction::NewMail
+                | ButtonAction::OpenFile(_)
+                | ButtonAction::BackToProfile => {
                     let Ok(mut text) = text_query.get_mut(children[0]) else {
                         return;
                     };
@@ -1,13 +1,18 @@
+use crate::game::ai::{agent::AgentRegistry, personality::Personality, AI_NAME};
 use bevy::prelude::*;
 use bevy_persistent::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 pub struct ProfilePlugin;
 
 impl Plugin for ProfilePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (setup, revert.after(setup)));
+        app.init_resource::<ConfigDir>()
+            .add_systems(Startup, (setup, revert, add_engines, add_agents).chain());
     }
 }
 
@@ -15,35 +20,106 @@ impl Plugin for ProfilePlugin {
 pub struct Profile {
     pub name: String,
     pub wins: u32,
+    #[serde(default)]
+    pub kind: ProfileKind,
 }
 
+impl Profile {
+    /// Whether the computer plays for this profile, either built in or through an engine.
+    pub fn is_ai(&self) -> bool {
+        self.kind != ProfileKind::Human
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub enum ProfileKind {
+    #[default]
+    Human,
+    /// An external program speaking the engine protocol, see `game::ai::engine`.
+    Engine(EngineConfig),
+    /// The built-in CPU with a play style of its own, see `game::ai::personality`.
+    Personality(Personality),
+    /// An agent registered by this name, see `game::ai::agent`.
+    Agent(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EngineConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// How long the engine gets per move before a random move is played for it.
+    #[serde(default = "default_move_time")]
+    pub move_time_ms: u64,
+}
+
+impl EngineConfig {
+    pub fn move_time(&self) -> Duration {
+        Duration::from_millis(self.move_time_ms)
+    }
+}
+
+fn default_move_time() -> u64 {
+    5000
+}
+
+/// Engine profiles, kept apart from `Profiles` since those are reset on every start.
+#[derive(Resource, Serialize, Deserialize)]
+struct Engines(Vec<Profile>);
+
 #[derive(Resource, Serialize, Deserialize)]
 pub struct Profiles(pub Vec<Profile>);
 
-fn setup(mut commands: Commands) {
-    let dir = dirs::config_dir()
+/// Where everything the game keeps between runs lives.
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
         .map(|dir| dir.join("bevy_mancala"))
-        .unwrap_or(Path::new("local").join("config"));
+        .unwrap_or(Path::new("local").join("config"))
+}
+
+/// The directory the running game reads and writes, `config_dir()` unless inserted before the
+/// plugins are added, as the tests do to keep off the player's files.
+#[derive(Resource, Clone)]
+pub struct ConfigDir(pub PathBuf);
 
+impl Default for ConfigDir {
+    fn default() -> Self {
+        Self(config_dir())
+    }
+}
+
+fn setup(mut commands: Commands, config_dir: Res<ConfigDir>) {
     commands.insert_resource(
         Persistent::<Profiles>::builder()
             .name("profiles")
             .format(StorageFormat::RonPrettyWithStructNames)
-            .path(dir.join("profiles.ron"))
-            .default(Profiles(vec![
-                Profile {
-                    name: "PL1".to_string(),
-                    wins: 0,
-                },
-                Profile {
-                    name: "PL2".to_string(),
+            .path(config_dir.0.join("profiles.ron"))
+            .default(Profiles(
+                vec![
+                    Profile {
+                        name: "PL1".to_string(),
+                        wins: 0,
+                        kind: ProfileKind::Human,
+                    },
+                    Profile {
+                        name: "PL2".to_string(),
+                        wins: 0,
+                        kind: ProfileKind::Human,
+                    },
+                    Profile {
+                        name: AI_NAME.to_string(),
+                        wins: 0,
+                        kind: ProfileKind::Agent(AI_NAME.to_string()),
+                    },
+                ]
+                .into_iter()
+                .chain(Personality::iter().map(|personality| Profile {
+                    name: personality.to_string(),
                     wins: 0,
-                },
-                Profile {
-                    name: "CPU".to_string(),
-                    wins: 0,
-                },
-            ]))
+                    kind: ProfileKind::Personality(personality),
+                }))
+                .collect(),
+            ))
             .revertible(true)
             .build()
             .expect("failed to initialize profiles"),
@@ -55,3 +131,56 @@ fn revert(mut profiles: ResMut<Persistent<Profiles>>) {
         .revert_to_default()
         .expect("failed to revert profiles to default");
 }
+
+fn add_engines(
+    mut commands: Commands,
+    mut profiles: ResMut<Persistent<Profiles>>,
+    config_dir: Res<ConfigDir>,
+) {
+    let engines = Persistent::<Engines>::builder()
+        .name("engines")
+        .format(StorageFormat::RonPrettyWithStructNames)
+        .path(config_dir.0.join("engines.ron"))
+        .default(Engines(vec![]))
+        .build()
+        .expect("failed to initialize engines");
+
+    let added: Vec<Profile> = engines
+        .0
+        .iter()
+        .filter(|profile| matches!(profile.kind, ProfileKind::Engine(_)))
+        .cloned()
+        .collect();
+
+    if !added.is_empty() {
+        profiles
+            .update(|profiles| profiles.0.extend(added.iter().cloned()))
+            .expect("failed to add engine profiles");
+    }
+
+    commands.insert_resource(engines);
+}
+
+/// Gives every registered agent without a profile one of its own, so it can be picked.
+fn add_agents(mut profiles: ResMut<Persistent<Profiles>>, registry: Res<AgentRegistry>) {
+    let added: Vec<Profile> = registry
+        .names()
+        .filter(|name| {
+            !profiles
+                .0
+                .iter()
+                .any(|profile| profile.kind == ProfileKind::Agent(name.to_string()))
+        })
+        .map(|name| Profile {
+            name: name.to_string(),
+            wins: 0,
+            kind: ProfileKind::Agent(name.to_string()),
+        })
+        .collect();
+
+    if !added.is_empty() {
+        profiles
+            .update(|profiles| profiles.0.extend(added.iter().cloned()))
+            .expect("failed to add agent profiles");
+    }
+}
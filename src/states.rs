@@ -1,10 +1,13 @@
 use bevy::prelude::*;
+use std::{fmt, str::FromStr};
 
 #[derive(States, SystemSet, Debug, Hash, PartialEq, Eq, Clone, Default)]
 pub enum AppState {
     #[default]
     Menu,
     Game,
+    /// Passed through for a single frame to tear down and set up a game with the same settings.
+    Restart,
 }
 
 #[derive(States, SystemSet, Debug, Hash, PartialEq, Eq, Copy, Clone, Default)]
@@ -14,11 +17,11 @@ pub enum GameMode {
     Capture,
 }
 
-impl ToString for GameMode {
-    fn to_string(&self) -> String {
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::Avalanche => "AVALANCHE".to_string(),
-            Self::Capture => "CAPTURE".to_string(),
+            Self::Avalanche => write!(f, "AVALANCHE"),
+            Self::Capture => write!(f, "CAPTURE"),
         }
     }
 }
@@ -28,3 +31,13 @@ impl GameMode {
         [GameMode::Avalanche, GameMode::Capture].iter().copied()
     }
 }
+
+impl FromStr for GameMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::iter()
+            .find(|mode| mode.to_string() == value)
+            .ok_or(())
+    }
+}
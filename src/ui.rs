@@ -1,3 +1,4 @@
+use crate::game::ai::personality::Personality;
 use bevy::prelude::*;
 
 pub struct UiPlugin;
@@ -25,6 +26,15 @@ pub struct UiAssets {
     pub marble: Handle<Image>,
     pub profile: Handle<Image>,
     pub plus: Handle<Image>,
+    pub info: Handle<Image>,
+    /// One for each personality, in order.
+    pub avatars: Vec<Handle<Image>>,
+}
+
+impl UiAssets {
+    pub fn avatar(&self, personality: Personality) -> Handle<Image> {
+        self.avatars[personality as usize].clone()
+    }
 }
 
 pub fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
@@ -33,6 +43,10 @@ pub fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
     let marble = asset_server.load("textures/marble.png");
     let profile = asset_server.load("textures/unknown.png");
     let plus = asset_server.load("textures/plus.png");
+    let info = asset_server.load("textures/info.png");
+    let avatars = Personality::iter()
+        .map(|personality| asset_server.load(personality.avatar()))
+        .collect();
 
     commands.insert_resource(UiAssets {
         font,
@@ -40,5 +54,7 @@ pub fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
         marble,
         profile,
         plus,
+        info,
+        avatars,
     });
 }
@@ -0,0 +1,155 @@
+//! Runs the game without a window or renderer, so whole games can be played out in tests.
+
+use bevy::{input::InputPlugin, prelude::*, time::TimeUpdateStrategy};
+use bevy_mancala::{
+    game::{
+        ai::endgame::SolveEndgames, AnimationState, Board, CurrentPlayer, GamePlugin, GameState,
+        Player, Slot, SlotPressEvent, Winner,
+    },
+    menu::MenuPlugin,
+    profile::{ConfigDir, ProfilePlugin},
+    states::{AppState, GameMode},
+    ui::UiPlugin,
+};
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+/// How much time passes every frame, whatever the frame actually took. Shorter than a fixed
+/// update, so no animation can start and finish between two state transitions.
+const FRAME_TIME: Duration = Duration::from_millis(15);
+/// Frames a press gets to be read and its animations queued.
+const PRESS_FRAMES: u32 = 5;
+/// Frames to wait for animations before giving up on them.
+const MAX_FRAMES: u32 = 10_000;
+
+/// Counts the harnesses started by this test binary, so each gets a directory of its own.
+static STARTED: AtomicU32 = AtomicU32::new(0);
+
+pub struct Harness {
+    app: App,
+    config_dir: PathBuf,
+}
+
+impl Harness {
+    /// Starts a game between two people, player one moving first.
+    pub fn new(game_mode: GameMode) -> Self {
+        // the game keeps its profiles and keys on disk, so every harness gets its own empty place
+        // for them, well away from the player's
+        let config_dir = env::temp_dir().join(format!(
+            "bevy_mancala-test-{}-{}",
+            process::id(),
+            STARTED.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut app = App::new();
+
+        app.insert_resource(ConfigDir(config_dir.clone()))
+            // nothing here plays perfectly, so there's no point solving the endgame tables
+            .insert_resource(SolveEndgames(false))
+            .add_plugins((MinimalPlugins, AssetPlugin::default(), InputPlugin))
+            // loaded by the game, even though nothing is drawn
+            .init_asset::<Image>()
+            .init_asset::<Font>()
+            .init_asset::<Mesh>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+            .init_state::<AppState>()
+            .init_state::<GameMode>()
+            .add_plugins((UiPlugin, MenuPlugin, GamePlugin, ProfilePlugin));
+
+        app.finish();
+        app.cleanup();
+        app.update();
+
+        app.world
+            .resource_mut::<NextState<GameMode>>()
+            .set(game_mode);
+        app.update();
+
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Game);
+
+        let mut harness = Self { app, config_dir };
+
+        // the marbles are placed once the board has been drawn
+        harness.settle();
+
+        harness
+    }
+
+    /// Plays the pit at `index` for whoever's turn it is, as if it had been clicked, then waits for
+    /// the move to be animated.
+    pub fn press(&mut self, index: usize) {
+        let slot = self.app.world.resource::<Board>().slots[index];
+
+        self.app.world.send_event(SlotPressEvent(slot));
+        self.settle();
+    }
+
+    /// Runs frames until every animation has played out and no state is left to change.
+    pub fn settle(&mut self) {
+        for _ in 0..PRESS_FRAMES {
+            self.app.update();
+        }
+
+        for _ in 0..MAX_FRAMES {
+            if self.is_settled() {
+                return;
+            }
+
+            self.app.update();
+        }
+
+        panic!("the board didn't settle within {MAX_FRAMES} frames");
+    }
+
+    fn is_settled(&self) -> bool {
+        let world = &self.app.world;
+        let animation_state = world.resource::<State<AnimationState>>().get();
+
+        // idle animations keep asking to stay idle, which changes nothing
+        *animation_state == AnimationState::Idle
+            && matches!(
+                world.resource::<State<GameState>>().get(),
+                GameState::Idle | GameState::Over
+            )
+            && world
+                .resource::<NextState<AnimationState>>()
+                .0
+                .as_ref()
+                .is_none_or(|next| next == animation_state)
+            && world.resource::<NextState<GameState>>().0.is_none()
+    }
+
+    pub fn counts(&mut self) -> [u32; Board::LENGTH] {
+        let mut counts = [0; Board::LENGTH];
+
+        for slot in self.app.world.query::<&Slot>().iter(&self.app.world) {
+            counts[slot.index] = slot.count;
+        }
+
+        counts
+    }
+
+    pub fn current_player(&self) -> Player {
+        self.app.world.resource::<CurrentPlayer>().0
+    }
+
+    pub fn game_state(&self) -> GameState {
+        self.app.world.resource::<State<GameState>>().get().clone()
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        self.app.world.resource::<Winner>().player
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.config_dir);
+    }
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use bevy_mancala::{
+    game::{rules::Position, GameState, Player},
+    states::GameMode,
+};
+use common::Harness;
+
+/// More than any game takes.
+const MAX_MOVES: usize = 500;
+
+#[test]
+fn landing_in_the_store_keeps_the_turn() {
+    let mut harness = Harness::new(GameMode::Capture);
+
+    harness.press(0);
+
+    assert_eq!(harness.counts(), [0, 7, 7, 7, 7, 7, 1, 6, 6, 6, 6, 6, 6, 0]);
+    assert_eq!(harness.current_player(), Player::One);
+}
+
+#[test]
+fn landing_on_the_other_side_passes_the_turn() {
+    let mut harness = Harness::new(GameMode::Capture);
+
+    harness.press(2);
+
+    assert_eq!(harness.counts(), [6, 6, 0, 7, 7, 7, 1, 7, 7, 6, 6, 6, 6, 0]);
+    assert_eq!(harness.current_player(), Player::Two);
+}
+
+#[test]
+fn landing_in_an_empty_pit_captures_the_opposite_pit() {
+    let mut harness = Harness::new(GameMode::Capture);
+
+    for index in [0, 1, 7, 0] {
+        harness.press(index);
+    }
+
+    assert_eq!(
+        harness.counts(),
+        [0, 0, 8, 8, 8, 8, 10, 0, 8, 7, 7, 0, 7, 1]
+    );
+    assert_eq!(harness.current_player(), Player::One);
+}
+
+#[test]
+fn avalanches_are_played_like_the_rules() {
+    let mut harness = Harness::new(GameMode::Avalanche);
+    let mut position = Position::new(Player::One);
+
+    for index in [1, 9, 4] {
+        position.play(index, GameMode::Avalanche);
+        harness.press(index);
+
+        assert_eq!(harness.counts(), position.counts);
+        assert_eq!(harness.current_player(), position.current);
+    }
+}
+
+#[test]
+fn games_end_with_the_leader_winning() {
+    for game_mode in GameMode::iter() {
+        let mut harness = Harness::new(game_mode);
+        let mut position = Position::new(Player::One);
+
+        for _ in 0..MAX_MOVES {
+            if position.is_over() {
+                break;
+            }
+
+            let index = position
+                .legal_moves()
+                .last()
+                .expect("a game in progress has moves");
+
+            position.play(index, game_mode);
+            harness.press(index);
+
+            assert_eq!(harness.current_player(), position.current, "{game_mode}");
+        }
+
+        assert!(position.is_over(), "{game_mode} didn't end");
+        assert_eq!(harness.game_state(), GameState::Over, "{game_mode}");
+        // what's swept up at the end is only animated, the pits keep their counts
+        assert_eq!(harness.counts(), position.counts, "{game_mode}");
+
+        position.sweep(game_mode);
+
+        assert_eq!(harness.winner(), position.leader(), "{game_mode}");
+    }
+}